@@ -0,0 +1,455 @@
+use super::{
+	error::ConversionError,
+	familiar_units, format_number, format_value, match_alias, number,
+	quantity::{base_units, describe, Quantity, DIMENSIONLESS},
+	registry::{registry, Unit},
+	ConversionOptions, Measurement, PreferredSystem,
+};
+
+const CALC_USAGE: &str =
+	"`!calc <expression> [in <unit>]`, e.g. `!calc 5 km + 300 m in miles` or `!calc 60 km / 45 min in mph`";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+	Number(f64),
+	Word(String),
+	Operator(char),
+}
+
+// how deeply parentheses and minus signs may nest before the parser gives up
+const MAX_DEPTH: usize = 32;
+
+const OPERATORS: &[char] = &['+', '-', '*', '×', '·', '/', '÷', '^', '(', ')'];
+
+// splits an expression into numbers, operators and words, with the byte offset each starts at
+fn lex(input: &str, options: &ConversionOptions) -> Result<Vec<(Token, usize)>, ConversionError> {
+	let mut tokens = vec![];
+	let mut chars = input.char_indices().peekable();
+	while let Some(&(start, c)) = chars.peek() {
+		if c.is_whitespace() {
+			chars.next();
+		} else if OPERATORS.contains(&c) {
+			chars.next();
+			tokens.push((Token::Operator(c), start));
+		} else if c.is_ascii_digit() || c == '.' || number::is_vulgar_fraction(c) {
+			let mut end = start;
+			while let Some(&(i, c)) = chars.peek() {
+				// separators and exponents have to be followed by a digit, "5.5e3" but "2 e"
+				let digit_after = input[i + c.len_utf8()..].starts_with(|c: char| c.is_ascii_digit());
+				if !(c.is_ascii_digit()
+					|| number::is_vulgar_fraction(c)
+					|| (matches!(c, '.' | ',' | 'e' | 'E') && digit_after))
+				{
+					break;
+				}
+				end = i + c.len_utf8();
+				chars.next();
+			}
+			let value = number::parse_number(&input[start..end], options.decimal_mark)?;
+			tokens.push((Token::Number(value), start));
+		} else {
+			let mut end = start;
+			while let Some(&(i, c)) = chars.peek() {
+				if c.is_whitespace() || c.is_ascii_digit() || OPERATORS.contains(&c) {
+					break;
+				}
+				end = i + c.len_utf8();
+				chars.next();
+			}
+			tokens.push((Token::Word(input[start..end].to_string()), start));
+		}
+	}
+	Ok(tokens)
+}
+
+// a recursive descent parser evaluating while it reads:
+// expression = term (("+" | "-") term)*
+// term = factor (("*" | "/") factor)*
+// factor = "-" factor | primary ("^" integer)?
+// primary = number unit? | unit | "(" expression ")"
+struct Parser<'a> {
+	tokens: &'a [Token],
+	position: usize,
+	depth: usize,
+	options: &'a ConversionOptions,
+	// the first unit of the expression, results are shown in it when nobody asked for a unit
+	first_unit: Option<&'static Unit>,
+}
+
+impl<'a> Parser<'a> {
+	fn new(tokens: &'a [Token], options: &'a ConversionOptions) -> Self {
+		Parser {
+			tokens,
+			position: 0,
+			depth: 0,
+			options,
+			first_unit: None,
+		}
+	}
+
+	fn peek(&self) -> Option<&Token> {
+		self.tokens.get(self.position)
+	}
+
+	fn next_operator(&mut self, operators: &[char]) -> Option<char> {
+		match self.peek() {
+			Some(&Token::Operator(operator)) if operators.contains(&operator) => {
+				self.position += 1;
+				Some(operator)
+			}
+			_ => None,
+		}
+	}
+
+	// reads the whole input, anything left over is an error
+	fn parse(&mut self) -> Result<Quantity, ConversionError> {
+		let value = self.expression()?;
+		match self.peek() {
+			None => Ok(value),
+			Some(_) => Err(ConversionError::UnexpectedInput(describe_tokens(
+				&self.tokens[self.position..],
+			))),
+		}
+	}
+
+	fn expression(&mut self) -> Result<Quantity, ConversionError> {
+		let mut value = self.term()?;
+		while let Some(operator) = self.next_operator(&['+', '-']) {
+			let term = self.term()?;
+			value = value.add(if operator == '-' { term.negate() } else { term })?;
+		}
+		Ok(value)
+	}
+
+	fn term(&mut self) -> Result<Quantity, ConversionError> {
+		let mut value = self.factor()?;
+		while let Some(operator) = self.next_operator(&['*', '×', '·', '/', '÷']) {
+			let factor = self.factor()?;
+			value = if matches!(operator, '/' | '÷') {
+				value.divide(factor)?
+			} else {
+				value.multiply(factor)
+			};
+		}
+		Ok(value)
+	}
+
+	fn factor(&mut self) -> Result<Quantity, ConversionError> {
+		self.depth += 1;
+		if self.depth > MAX_DEPTH {
+			return Err(ConversionError::Usage(CALC_USAGE));
+		}
+		let value = if self.next_operator(&['-']).is_some() {
+			self.factor()?.negate()
+		} else {
+			let value = self.primary()?;
+			value.power(self.exponent()?)
+		};
+		self.depth -= 1;
+		Ok(value)
+	}
+
+	// the "^2" of "m^2", 1 without one
+	fn exponent(&mut self) -> Result<i8, ConversionError> {
+		if self.next_operator(&['^']).is_none() {
+			return Ok(1);
+		}
+		let sign = if self.next_operator(&['-']).is_some() {
+			-1
+		} else {
+			1
+		};
+		match self.peek() {
+			Some(&Token::Number(exponent)) if exponent.fract() == 0.0 && exponent.abs() <= 9.0 => {
+				self.position += 1;
+				Ok(sign * exponent as i8)
+			}
+			_ => Err(ConversionError::UnexpectedInput(describe_tokens(
+				&self.tokens[self.position.saturating_sub(1)..],
+			))),
+		}
+	}
+
+	fn primary(&mut self) -> Result<Quantity, ConversionError> {
+		match self.peek().cloned() {
+			Some(Token::Number(value)) => {
+				self.position += 1;
+				match (self.unit()?, self.peek()) {
+					// "5 km^2" squares the unit, not the five
+					(Some(unit), _) => Ok(Quantity::number(value).multiply(unit.power(self.exponent()?))),
+					(None, Some(Token::Word(word))) => Err(ConversionError::UnknownUnit(word.clone())),
+					(None, _) => Ok(Quantity::number(value)),
+				}
+			}
+			Some(Token::Operator('(')) => {
+				self.position += 1;
+				let value = self.expression()?;
+				match self.next_operator(&[')']) {
+					Some(_) => Ok(value),
+					None => Err(ConversionError::Usage(CALC_USAGE)),
+				}
+			}
+			Some(Token::Word(word)) => match self.unit()? {
+				Some(unit) => Ok(unit),
+				None => Err(ConversionError::UnknownUnit(word)),
+			},
+			_ => Err(ConversionError::Usage(CALC_USAGE)),
+		}
+	}
+
+	// one unit of whatever the next words name, "km", "fl oz" or "miles per hour"
+	fn unit(&mut self) -> Result<Option<Quantity>, ConversionError> {
+		let words: Vec<_> = self.tokens[self.position..]
+			.iter()
+			.map_while(|token| match token {
+				Token::Word(word) => Some(word.clone()),
+				_ => None,
+			})
+			.collect();
+		// "3 in" are inches here, calculations don't say "in" for anything else
+		let (len, mut units) = match match_alias(&words, true) {
+			Some(unit) => unit,
+			None => return Ok(None),
+		};
+		familiar_units(&mut units, self.options.system);
+		let unit = units[0];
+		if unit.offset != 0.0 {
+			return Err(ConversionError::AbsoluteTemperature(unit));
+		}
+		self.position += len;
+		self.first_unit.get_or_insert(unit);
+		Ok(Some(Quantity::of(1.0, unit)))
+	}
+}
+
+fn describe_tokens(tokens: &[Token]) -> String {
+	tokens
+		.iter()
+		.map(|token| match token {
+			Token::Number(value) => value.to_string(),
+			Token::Word(word) => word.clone(),
+			Token::Operator(operator) => operator.to_string(),
+		})
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+// the unit a result is best shown in: the unit the expression started with, or the unit of a dimension that
+// is another's inverse, "300 miles / 10 gal" is a fuel economy and "10 L / 100 km" a fuel consumption rather
+// than a tiny area, in the units the audience uses for it
+fn result_unit(value: Quantity, first_unit: Option<&'static Unit>, system: PreferredSystem) -> Option<&'static Unit> {
+	first_unit
+		.filter(|unit| unit.dimensions == value.dimensions)
+		.or_else(|| {
+			registry()
+				.units()
+				.filter(|unit| unit.dimensions == value.dimensions && registry().has_reciprocal(unit))
+				.filter(|unit| {
+					describe(value.dimensions) == unit.dimension
+						|| first_unit.is_some_and(|first| first.dimension == "volume")
+				})
+				.min_by_key(|unit| !system.includes(unit.system))
+		})
+}
+
+// shows a result without a requested unit in the unit it's best shown in,
+// or the SI unit of its dimensions when there is none ("60 km / 45 min" in m/s)
+fn format_result(value: Quantity, first_unit: Option<&'static Unit>, system: PreferredSystem) -> String {
+	if value.dimensions == DIMENSIONLESS {
+		return format_number(value.value);
+	}
+	let unit = result_unit(value, first_unit, system).or_else(|| {
+		registry()
+			.units()
+			.find(|unit| unit.dimensions == value.dimensions && unit.factor == 1.0 && unit.offset == 0.0)
+	});
+	match unit {
+		Some(unit) => {
+			let (value, symbol) = Measurement::from_unit(unit.to_unit(value.value), unit)
+				.with_friendly_prefix()
+				.destruct();
+			format_value(value, &symbol)
+		}
+		None => format_value(value.value, &base_units(value.dimensions)),
+	}
+}
+
+// evaluates a unit written as an expression, "ft/min" or "kWh / day", into what one of it is,
+// with its registry unit when it names a single one ("sq ft")
+pub fn unit_expression(
+	text: &str,
+	options: &ConversionOptions,
+) -> Result<(Quantity, Option<&'static Unit>), ConversionError> {
+	// "L/100km" is a unit of its own rather than liters divided by 100 km
+	let mut units: Vec<_> = registry()
+		.lookup_all(text)
+		.filter(|unit| unit.offset == 0.0)
+		.collect();
+	familiar_units(&mut units, options.system);
+	if let Some(&unit) = units.first() {
+		return Ok((Quantity::of(1.0, unit), Some(unit)));
+	}
+	let tokens: Vec<_> = lex(text, options)?
+		.into_iter()
+		.map(|(token, _)| token)
+		.collect();
+	if tokens.is_empty() {
+		return Err(ConversionError::UnknownUnit(text.to_string()));
+	}
+	let mut parser = Parser::new(&tokens, options);
+	let unit = parser.parse()?;
+	let single = tokens.iter().all(|token| matches!(token, Token::Word(_)));
+	Ok((unit, parser.first_unit.filter(|_| single)))
+}
+
+// handles the arguments of `!calc`, "5 km + 300 m in miles" or "2 lbs + 12 oz in grams"
+pub fn calc_command(args: &str, options: &ConversionOptions) -> Result<String, ConversionError> {
+	let tokens = lex(args, options)?;
+	// everything after the last "in" or "to" is the unit of the result, so "5 ft + 3 in in cm" can add inches
+	let separator = |tokens: &[(Token, usize)]| {
+		tokens.iter().rposition(
+			|(token, _)| matches!(token, Token::Word(word) if word.eq_ignore_ascii_case("in") || word.eq_ignore_ascii_case("to")),
+		)
+	};
+	let target = match separator(&tokens) {
+		// "5 ft + 3 in" ends in inches rather than in a missing unit
+		Some(last) if last + 1 == tokens.len() && last > 0 && matches!(tokens[last - 1].0, Token::Number(_)) => None,
+		// "30 cm in in" asks for inches
+		Some(last) if last + 1 == tokens.len() => separator(&tokens[..last]).or(Some(last)),
+		target => target,
+	};
+	let (expression, target) = match target {
+		Some(target) => (&tokens[..target], Some(&tokens[target + 1..])),
+		None => (&tokens[..], None),
+	};
+	if expression.is_empty() || target.is_some_and(<[_]>::is_empty) {
+		return Err(ConversionError::Usage(CALC_USAGE));
+	}
+	let end = target.map_or(args.len(), |_| tokens[expression.len()].1);
+	let (expression, _): (Vec<_>, Vec<_>) = expression.iter().cloned().unzip();
+	let mut parser = Parser::new(&expression, options);
+	let value = parser.parse()?;
+	let finite = |value: f64| {
+		if value.is_finite() {
+			Ok(value)
+		} else {
+			Err(ConversionError::Overflow)
+		}
+	};
+	finite(value.value)?;
+	let result = match target {
+		Some(target) => {
+			let target_text = args[target[0].1..].trim();
+			let (unit, named) = unit_expression(target_text, options)?;
+			// a single unit is shown with its symbol, anything else the way it was written
+			let symbol = named.map_or_else(|| target_text.to_string(), |unit| unit.symbol.clone());
+			let result = match named {
+				Some(named) => value.value_in(named),
+				None => value.value_per(unit),
+			}
+			.map_err(|why| match why {
+				// named after the unit the value was written in, "5 L/100km" are a fuel consumption
+				ConversionError::MismatchedDimensions(dimensions, target) => ConversionError::MismatchedDimensions(
+					result_unit(value, parser.first_unit, options.system)
+						.map_or(dimensions, |unit| unit.dimension.clone()),
+					target,
+				),
+				why => why,
+			})?;
+			format_value(finite(result)?, &symbol)
+		}
+		None => format_result(value, parser.first_unit, options.system),
+	};
+	Ok(format!("{} is {}", args[..end].trim(), result))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn calc(args: &str) -> Result<String, ConversionError> {
+		calc_command(args, &ConversionOptions::default())
+	}
+
+	#[test]
+	fn calculates_with_units() {
+		let cases = [
+			("5 km + 300 m in miles", "5 km + 300 m is 3.29 miles"),
+			("2 lbs + 12 oz in grams", "2 lbs + 12 oz is 1247.38 grams"),
+			("60 km / 45 min in mph", "60 km / 45 min is 49.71 mph"),
+			("60 km / 45 min", "60 km / 45 min is 22.22 m/s"),
+			("5 km + 300 m", "5 km + 300 m is 5.30 km"),
+			("(2 + 3) * 4 ft to inches", "(2 + 3) * 4 ft is 240.00 in"),
+			("3 m * 4 m in sq ft", "3 m * 4 m is 129.17 ft²"),
+			("10 m^2 / 2 m", "10 m^2 / 2 m is 5.00 m"),
+			("100 km / 2 h in km/h", "100 km / 2 h is 50.00 km/h"),
+			("-2 Δc + 10 Δc in Δf", "-2 Δc + 10 Δc is 14.40 Δ℉"),
+			("1 kWh / 2 h in W", "1 kWh / 2 h is 500.00 W"),
+			("10 km / 5 km", "10 km / 5 km is 2.00"),
+			("1,5 km + ½ km in m", "1,5 km + ½ km is 2000.00 m"),
+			(
+				"300 miles / 10 gal in mpg",
+				"300 miles / 10 gal is 30.00 US mpg",
+			),
+			("5 ft + 3 in in cm", "5 ft + 3 in is 160.02 cm"),
+			("5 ft + 3 in", "5 ft + 3 in is 5.25 ft"),
+			("30 in to ft", "30 in is 2.50 ft"),
+			("30 cm in in", "30 cm is 11.81 in"),
+			("30 cm to in", "30 cm is 11.81 in"),
+			("300 miles / 10 gal", "300 miles / 10 gal is 30.00 US mpg"),
+			("10 L / 100 km", "10 L / 100 km is 10.00 L/100km"),
+			("3 m * 4 m", "3 m * 4 m is 12.00 m²"),
+		];
+		for (args, expected) in cases {
+			assert_eq!(calc(args), Ok(String::from(expected)), "{}", args);
+		}
+	}
+
+	#[test]
+	fn reports_calculation_errors() {
+		assert_eq!(
+			calc("5 km + 3 kg"),
+			Err(ConversionError::MismatchedDimensions(
+				String::from("length"),
+				String::from("mass")
+			))
+		);
+		assert_eq!(
+			calc("5 km in kg").unwrap_err().to_string(),
+			"length and mass don't match"
+		);
+		assert_eq!(
+			calc("8 L/100km in m").unwrap_err().to_string(),
+			"fuel consumption and length don't match"
+		);
+		assert_eq!(
+			calc("8 m in L/100km").unwrap_err().to_string(),
+			"length and fuel consumption don't match"
+		);
+		assert_eq!(calc("5 km / 0"), Err(ConversionError::DivisionByZero));
+		assert_eq!(
+			calc("1e300 km * 1e300 km * 1e300 km"),
+			Err(ConversionError::Overflow)
+		);
+		assert_eq!(calc("1e300 km in nm"), Err(ConversionError::Overflow));
+		assert_eq!(
+			calc("5 parsecs + 1 km"),
+			Err(ConversionError::UnknownUnit(String::from("parsecs")))
+		);
+		assert_eq!(
+			calc("20 c + 5 c").unwrap_err().to_string(),
+			"I can't calculate with ℃ temperatures, only with differences like Δ℃"
+		);
+		assert_eq!(calc("(5 km"), Err(ConversionError::Usage(CALC_USAGE)));
+		assert_eq!(calc("5 km in"), Err(ConversionError::Usage(CALC_USAGE)));
+		assert_eq!(calc(""), Err(ConversionError::Usage(CALC_USAGE)));
+		assert_eq!(
+			calc(&format!("{}5 km{}", "(".repeat(100), ")".repeat(100))),
+			Err(ConversionError::Usage(CALC_USAGE))
+		);
+		assert_eq!(
+			calc("5 km 3"),
+			Err(ConversionError::UnexpectedInput(String::from("3")))
+		);
+	}
+}
@@ -0,0 +1,278 @@
+use serenity::{
+	client::Context,
+	framework::standard::{macros::command, Args, CommandResult},
+	model::{
+		channel::Message,
+		id::{ChannelId, GuildId, MessageId, UserId},
+	},
+	prelude::{RwLock, TypeMapKey},
+};
+use std::{
+	collections::{HashMap, VecDeque},
+	sync::Arc,
+};
+
+use crate::settings::conversion_options;
+
+// how many source messages are remembered so their conversion can follow edits and deletions
+const TRACKED_REPLIES: usize = 1000;
+
+pub struct ConversionReplies;
+
+impl TypeMapKey for ConversionReplies {
+	type Value = Arc<RwLock<RecentReplies>>;
+}
+
+// what the bot did about a message it has seen
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Reply {
+	// the message had nothing to convert
+	Nothing,
+	// the conversion is on its way, edits don't send another one
+	Sending,
+	Sent(MessageId),
+}
+
+// the bot's conversion reply for each recent source message, oldest ones are forgotten first
+#[derive(Debug, Default)]
+pub struct RecentReplies {
+	replies: HashMap<MessageId, Reply>,
+	order: VecDeque<MessageId>,
+}
+
+impl RecentReplies {
+	fn insert(&mut self, source: MessageId, reply: Reply) {
+		if self.replies.insert(source, reply).is_none() {
+			self.order.push_back(source);
+		}
+		while self.order.len() > TRACKED_REPLIES {
+			if let Some(oldest) = self.order.pop_front() {
+				self.replies.remove(&oldest);
+			}
+		}
+	}
+
+	fn get(&self, source: MessageId) -> Option<Reply> {
+		self.replies.get(&source).copied()
+	}
+
+	// claims the reply to a message seen without one, false if it has or gets one already
+	fn reserve(&mut self, source: MessageId) -> bool {
+		match self.replies.get_mut(&source) {
+			Some(reply) if *reply == Reply::Nothing => {
+				*reply = Reply::Sending;
+				true
+			}
+			_ => false,
+		}
+	}
+
+	// records a reserved reply as sent, false if its message was deleted in the meantime
+	fn finish(&mut self, source: MessageId, sent: MessageId) -> bool {
+		match self.replies.get_mut(&source) {
+			Some(reply) if *reply == Reply::Sending => {
+				*reply = Reply::Sent(sent);
+				true
+			}
+			_ => false,
+		}
+	}
+
+	fn remove(&mut self, source: MessageId) -> Option<Reply> {
+		let reply = self.replies.remove(&source)?;
+		self.order.retain(|&id| id != source);
+		Some(reply)
+	}
+}
+
+async fn replies_lock(ctx: &Context) -> Arc<RwLock<RecentReplies>> {
+	let data_read = ctx.data.read().await;
+	data_read
+		.get::<ConversionReplies>()
+		.expect("expected a ConversionReplies")
+		.clone()
+}
+
+#[command]
+pub async fn convert(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	let options = conversion_options(ctx, msg.guild_id, Some(msg.author.id)).await;
+	let reply = match super::convert_command(args.rest(), &options) {
+		Ok(reply) => reply,
+		Err(why) => why.to_string(),
+	};
+	msg.reply(ctx, reply).await?;
+	Ok(())
+}
+
+#[command]
+pub async fn calc(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	let options = conversion_options(ctx, msg.guild_id, Some(msg.author.id)).await;
+	let reply = match super::calc_command(args.rest(), &options) {
+		Ok(reply) => reply,
+		Err(why) => why.to_string(),
+	};
+	msg.reply(ctx, reply).await?;
+	Ok(())
+}
+
+#[command]
+#[sub_commands(scale)]
+pub async fn recipe(ctx: &Context, msg: &Message) -> CommandResult {
+	msg.reply(ctx, format!("Usage: {}", super::RECIPE_USAGE))
+		.await?;
+	Ok(())
+}
+
+// "!recipe scale x1.5" with the ingredients on the following lines
+#[command]
+async fn scale(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	let options = conversion_options(ctx, msg.guild_id, Some(msg.author.id)).await;
+	let reply = match super::scale_recipe(args.rest(), &options) {
+		Ok(reply) => reply,
+		Err(why) => why.to_string(),
+	};
+	msg.reply(ctx, reply).await?;
+	Ok(())
+}
+
+// records the outcome of sending a reserved reply, a reply to a message deleted in the meantime goes too
+async fn finish_reply(
+	ctx: &Context,
+	replies: &RwLock<RecentReplies>,
+	channel_id: ChannelId,
+	source: MessageId,
+	sent: serenity::Result<Message>,
+) -> CommandResult {
+	let sent = match sent {
+		Ok(sent) => sent,
+		Err(why) => {
+			replies.write().await.insert(source, Reply::Nothing);
+			return Err(why.into());
+		}
+	};
+	if !replies.write().await.finish(source, sent.id) {
+		channel_id.delete_message(&ctx.http, sent.id).await?;
+	}
+	Ok(())
+}
+
+// replies to a new message with its conversion and remembers the reply,
+// messages without one are remembered too so an edit can still add it
+pub async fn send_conversion(ctx: &Context, msg: &Message) -> CommandResult {
+	let replies = replies_lock(ctx).await;
+	let options = conversion_options(ctx, msg.guild_id, Some(msg.author.id)).await;
+	let conversion = if super::is_command(&msg.content) {
+		Err(super::ConversionError::NothingToConvert)
+	} else {
+		super::respond_to_msg(&msg.content, &options)
+	};
+	match conversion {
+		Ok(reply) => {
+			// reserved before sending so an edit arriving meanwhile doesn't send a second reply
+			replies.write().await.insert(msg.id, Reply::Sending);
+			let sent = msg.reply(ctx, reply).await;
+			finish_reply(ctx, &replies, msg.channel_id, msg.id, sent).await
+		}
+		Err(_) => {
+			replies.write().await.insert(msg.id, Reply::Nothing);
+			Ok(())
+		}
+	}
+}
+
+// keeps the conversion in line with an edited message: the reply is edited, removed when
+// the message no longer has anything to convert, or sent if the edit added a measurement,
+// messages the bot hasn't seen are left alone because they may have a reply from before a restart
+pub async fn update_conversion(
+	ctx: &Context,
+	channel_id: ChannelId,
+	message_id: MessageId,
+	guild_id: Option<GuildId>,
+	author_id: Option<UserId>,
+	content: &str,
+) -> CommandResult {
+	let options = conversion_options(ctx, guild_id, author_id).await;
+	// a message edited into a command loses its conversion
+	let conversion = if super::is_command(content) {
+		Err(super::ConversionError::NothingToConvert)
+	} else {
+		super::respond_to_msg(content, &options)
+	};
+	let replies = replies_lock(ctx).await;
+	let reply = replies.read().await.get(message_id);
+	match (conversion, reply) {
+		(Ok(conversion), Some(Reply::Sent(reply_id))) => {
+			channel_id
+				.edit_message(&ctx.http, reply_id, |m| m.content(conversion))
+				.await?;
+		}
+		(Ok(conversion), Some(Reply::Nothing)) => {
+			if !replies.write().await.reserve(message_id) {
+				return Ok(());
+			}
+			let sent = channel_id
+				.send_message(&ctx.http, |m| {
+					m.content(conversion)
+						.reference_message((channel_id, message_id))
+				})
+				.await;
+			finish_reply(ctx, &replies, channel_id, message_id, sent).await?;
+		}
+		(Err(_), Some(Reply::Sent(reply_id))) => {
+			replies.write().await.insert(message_id, Reply::Nothing);
+			channel_id.delete_message(&ctx.http, reply_id).await?;
+		}
+		_ => {}
+	}
+	Ok(())
+}
+
+// removes the conversion of a deleted message
+pub async fn delete_conversion(ctx: &Context, channel_id: ChannelId, message_id: MessageId) -> CommandResult {
+	let reply = replies_lock(ctx).await.write().await.remove(message_id);
+	if let Some(Reply::Sent(reply_id)) = reply {
+		channel_id.delete_message(&ctx.http, reply_id).await?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn recent_replies_forget_the_oldest() {
+		let mut replies = RecentReplies::default();
+		for id in 0..TRACKED_REPLIES as u64 + 2 {
+			replies.insert(MessageId(id), Reply::Sent(MessageId(id + 10_000)));
+		}
+		assert_eq!(replies.get(MessageId(0)), None);
+		assert_eq!(replies.get(MessageId(1)), None);
+		assert_eq!(
+			replies.get(MessageId(2)),
+			Some(Reply::Sent(MessageId(10_002)))
+		);
+		assert_eq!(
+			replies.remove(MessageId(2)),
+			Some(Reply::Sent(MessageId(10_002)))
+		);
+		assert_eq!(replies.remove(MessageId(2)), None);
+		assert_eq!(replies.order.len(), TRACKED_REPLIES - 1);
+	}
+
+	#[test]
+	fn recent_replies_are_reserved_once() {
+		let mut replies = RecentReplies::default();
+		// never seen, maybe answered before a restart
+		assert!(!replies.reserve(MessageId(1)));
+		replies.insert(MessageId(1), Reply::Nothing);
+		assert!(replies.reserve(MessageId(1)));
+		assert!(!replies.reserve(MessageId(1)));
+		assert!(replies.finish(MessageId(1), MessageId(2)));
+		assert_eq!(replies.get(MessageId(1)), Some(Reply::Sent(MessageId(2))));
+		// deleted while its reply was being sent
+		replies.insert(MessageId(3), Reply::Sending);
+		replies.remove(MessageId(3));
+		assert!(!replies.finish(MessageId(3), MessageId(4)));
+	}
+}
@@ -0,0 +1,193 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+// how sure the bot has to be that a message means a measurement before it replies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Strictness {
+	Relaxed,
+	#[default]
+	Normal,
+	Strict,
+}
+
+impl Strictness {
+	pub fn accepts(self, confidence: f64) -> bool {
+		let threshold = match self {
+			Strictness::Relaxed => 0.2,
+			Strictness::Normal => 0.5,
+			// ambiguous aliases have to be part of a compound or glued to their number in a sentence
+			// that reads like a measurement ("it's 30c")
+			Strictness::Strict => 0.8,
+		};
+		confidence >= threshold
+	}
+}
+
+impl FromStr for Strictness {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"relaxed" => Ok(Strictness::Relaxed),
+			"normal" => Ok(Strictness::Normal),
+			"strict" => Ok(Strictness::Strict),
+			_ => Err(format!("'{}' isn't relaxed, normal or strict", s)),
+		}
+	}
+}
+
+impl fmt::Display for Strictness {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let name = match self {
+			Strictness::Relaxed => "relaxed",
+			Strictness::Normal => "normal",
+			Strictness::Strict => "strict",
+		};
+		write!(f, "{}", name)
+	}
+}
+
+// words in front of a number that make it a count or a rank rather than a measurement ("grade 5 f")
+const NOT_A_MEASUREMENT_BEFORE: &[&str] = &[
+	"act", "chapter", "class", "ep", "episode", "game", "gen", "grade", "level", "lvl", "page", "part", "phase",
+	"rank", "room", "round", "score", "season", "step", "tier", "top", "version", "vs",
+];
+
+// words in front of a number that usually introduce a measurement ("it's 30 c")
+const MEASUREMENT_BEFORE: &[&str] = &[
+	"about",
+	"almost",
+	"am",
+	"approx",
+	"approximately",
+	"are",
+	"around",
+	"ca",
+	"circa",
+	"drove",
+	"he's",
+	"i'm",
+	"is",
+	"it's",
+	"its",
+	"nearly",
+	"only",
+	"ran",
+	"roughly",
+	"she's",
+	"temp",
+	"temperature",
+	"they're",
+	"walked",
+	"was",
+	"we're",
+	"weigh",
+	"weighed",
+	"weighs",
+	"you're",
+	"~",
+	"≈",
+];
+
+// words after a unit that usually describe a measurement ("6 ft tall")
+const MEASUREMENT_AFTER: &[&str] = &[
+	"away", "deep", "heavy", "high", "long", "of", "outside", "per", "rn", "tall", "today", "tomorrow", "tonight",
+	"where", "wide",
+];
+
+// words in front of "by" or "of" that make a temperature a difference ("rose by 10 c")
+const DIFFERENCE_BEFORE: &[&str] = &[
+	"change",
+	"changed",
+	"cooled",
+	"decrease",
+	"decreased",
+	"difference",
+	"down",
+	"drop",
+	"dropped",
+	"fell",
+	"increase",
+	"increased",
+	"rise",
+	"risen",
+	"rose",
+	"up",
+	"warmed",
+];
+
+// words after a temperature that make it a difference ("10 c warmer")
+const DIFFERENCE_AFTER: &[&str] = &[
+	"apart",
+	"colder",
+	"cooler",
+	"difference",
+	"hotter",
+	"higher",
+	"less",
+	"lower",
+	"more",
+	"warmer",
+];
+
+// a number followed by a unit in a tokenized message
+pub struct Candidate<'a> {
+	pub words: &'a [String],
+	// index of the number, or of the first number of a range
+	pub number: usize,
+	// index of the first word after the quantity
+	pub end: usize,
+	// the unit was glued to the number, "30c" rather than "30 c"
+	pub glued: bool,
+	// the number was written out, "five miles"
+	pub spelled: bool,
+	// the quantity is a range, "20-25 c"
+	pub range: bool,
+	pub ambiguous: bool,
+	pub compound: bool,
+}
+
+impl Candidate<'_> {
+	pub fn confidence(&self) -> f64 {
+		let mut confidence: f64 = if self.ambiguous { 0.3 } else { 1.0 };
+		if self.compound {
+			confidence += 0.5;
+		}
+		// written out numbers are often just words, "one foot in the door", they need the words around them
+		// to read as a measurement
+		if self.spelled {
+			confidence -= 0.6;
+		}
+		let before = self.number.checked_sub(1).map(|i| self.words[i].as_str());
+		let after = self.words.get(self.end).map(String::as_str);
+		if before.is_some_and(|word| MEASUREMENT_BEFORE.contains(&word))
+			|| after.is_some_and(|word| MEASUREMENT_AFTER.contains(&word))
+			|| self.describes_difference()
+			|| self.range
+		{
+			confidence += 0.3;
+			// gluing only backs up the words around it, "5m views" and "99c" aren't measurements
+			if self.glued {
+				confidence += 0.2;
+			}
+		}
+		if before.is_some_and(|word| NOT_A_MEASUREMENT_BEFORE.contains(&word)) {
+			confidence -= 0.6;
+		}
+		confidence.clamp(0.0, 1.0)
+	}
+
+	// whether a temperature describes a change rather than a reading, "rose by 10 c" or "10 c warmer"
+	pub fn describes_difference(&self) -> bool {
+		let before = |offset: usize| {
+			self.number
+				.checked_sub(offset)
+				.map(|i| self.words[i].as_str())
+		};
+		let after = self.words.get(self.end).map(String::as_str);
+		matches!(before(1), Some("by") | Some("of")) && before(2).is_some_and(|word| DIFFERENCE_BEFORE.contains(&word))
+			|| after.is_some_and(|word| DIFFERENCE_AFTER.contains(&word))
+	}
+}
@@ -0,0 +1,49 @@
+use std::{error::Error, fmt};
+
+use super::registry::Unit;
+
+#[derive(Debug, PartialEq)]
+pub enum ConversionError {
+	// the message doesn't contain anything that can be converted
+	NothingToConvert,
+	UnknownUnit(String),
+	InvalidNumber(String),
+	IncompatibleUnits(&'static Unit, &'static Unit),
+	UnexpectedInput(String),
+	// quantities of different dimensions were added or compared, holds the names of both dimensions
+	MismatchedDimensions(String, String),
+	DivisionByZero,
+	// a calculation went beyond what an f64 holds, "1e300 km * 1e300 km * 1e300 km"
+	Overflow,
+	// temperatures with an offset ("20 ℃") can't be added or multiplied, their differences can
+	AbsoluteTemperature(&'static Unit),
+	// a command was called without the arguments it needs, holds the command's usage
+	Usage(&'static str),
+}
+
+impl fmt::Display for ConversionError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ConversionError::NothingToConvert => write!(f, "There is nothing to convert"),
+			ConversionError::UnknownUnit(unit) => write!(f, "I don't know the unit '{}'", unit),
+			ConversionError::InvalidNumber(number) => write!(f, "'{}' is not a number", number),
+			ConversionError::IncompatibleUnits(from, to) => write!(
+				f,
+				"{} is {}, {} is {}",
+				from.symbol, from.dimension, to.symbol, to.dimension
+			),
+			ConversionError::UnexpectedInput(input) => write!(f, "I don't know what to do with '{}'", input),
+			ConversionError::MismatchedDimensions(left, right) => write!(f, "{} and {} don't match", left, right),
+			ConversionError::DivisionByZero => write!(f, "I can't divide by zero"),
+			ConversionError::Overflow => write!(f, "The result is too large for me"),
+			ConversionError::AbsoluteTemperature(unit) => write!(
+				f,
+				"I can't calculate with {} temperatures, only with differences like Δ{}",
+				unit.symbol, unit.symbol
+			),
+			ConversionError::Usage(usage) => write!(f, "Usage: {}", usage),
+		}
+	}
+}
+
+impl Error for ConversionError {}
@@ -0,0 +1,197 @@
+use super::{
+	quantity::{Dimensions, Quantity},
+	registry::Unit,
+};
+
+// mass per volume, kilograms per cubic meter
+const DENSITY: Dimensions = [-3, 1, 0, 0];
+
+// something a recipe measures by volume or by weight, "2 cups flour" or "250 g sugar"
+#[derive(Debug, PartialEq)]
+pub struct Ingredient {
+	pub name: &'static str,
+	// in kilograms per cubic meter, which is also grams per liter
+	density: f64,
+	// poured rather than spooned, a bottle of it stays a volume
+	liquid: bool,
+	aliases: &'static [&'static str],
+}
+
+// densities of the ingredients as they are usually measured, spooned flour and packed brown sugar
+const INGREDIENTS: &[Ingredient] = &[
+	Ingredient {
+		name: "flour",
+		density: 528.0,
+		liquid: false,
+		aliases: &[
+			"flour",
+			"all-purpose flour",
+			"all purpose flour",
+			"plain flour",
+			"ap flour",
+		],
+	},
+	Ingredient {
+		name: "sugar",
+		density: 845.0,
+		liquid: false,
+		aliases: &["sugar", "granulated sugar", "white sugar", "caster sugar"],
+	},
+	Ingredient {
+		name: "brown sugar",
+		density: 930.0,
+		liquid: false,
+		aliases: &["brown sugar"],
+	},
+	Ingredient {
+		name: "powdered sugar",
+		density: 507.0,
+		liquid: false,
+		aliases: &["powdered sugar", "icing sugar", "confectioners sugar"],
+	},
+	Ingredient {
+		name: "butter",
+		density: 959.0,
+		liquid: false,
+		aliases: &["butter"],
+	},
+	Ingredient {
+		name: "water",
+		density: 1000.0,
+		liquid: true,
+		aliases: &["water"],
+	},
+	Ingredient {
+		name: "milk",
+		density: 1035.0,
+		liquid: true,
+		aliases: &["milk"],
+	},
+	Ingredient {
+		name: "cream",
+		density: 1010.0,
+		liquid: true,
+		aliases: &["cream", "heavy cream", "whipping cream"],
+	},
+	Ingredient {
+		name: "oil",
+		density: 920.0,
+		liquid: true,
+		aliases: &["oil", "vegetable oil", "olive oil"],
+	},
+	Ingredient {
+		name: "honey",
+		density: 1420.0,
+		liquid: true,
+		aliases: &["honey"],
+	},
+	Ingredient {
+		name: "rice",
+		density: 780.0,
+		liquid: false,
+		aliases: &["rice"],
+	},
+	Ingredient {
+		name: "oats",
+		density: 380.0,
+		liquid: false,
+		aliases: &["oats", "rolled oats"],
+	},
+	Ingredient {
+		name: "cocoa",
+		density: 360.0,
+		liquid: false,
+		aliases: &["cocoa", "cocoa powder"],
+	},
+	Ingredient {
+		name: "salt",
+		density: 1220.0,
+		liquid: false,
+		aliases: &["salt", "table salt"],
+	},
+];
+
+impl Ingredient {
+	// weighs a volume of the ingredient or measures out a mass of it, anything else stays as it is
+	pub fn convert(&self, quantity: Quantity, dimensions: Dimensions) -> Quantity {
+		let density = Quantity {
+			value: self.density,
+			dimensions: DENSITY,
+		};
+		[
+			quantity.multiply(density),
+			quantity.multiply(density.power(-1)),
+		]
+		.iter()
+		.copied()
+		.find(|converted| converted.dimensions == dimensions)
+		.unwrap_or(quantity)
+	}
+
+	// whether a volume of the ingredient reads better as its weight, a cup of milk does but a gallon of it doesn't
+	pub fn weighed_in(&self, unit: &Unit) -> bool {
+		!self.liquid || ["cup", "tablespoon", "teaspoon"].contains(&unit.name.as_str())
+	}
+}
+
+// whether an amount of an ingredient can be given in `unit`
+pub fn measures(unit: &Unit) -> bool {
+	unit.dimension == "mass" || unit.dimension == "volume"
+}
+
+// the ingredient named at the start of `words` and how many words it takes, "of flour" or "brown sugar"
+pub fn match_ingredient(words: &[String]) -> Option<(usize, &'static Ingredient)> {
+	let of = usize::from(
+		words
+			.first()
+			.is_some_and(|word| word.eq_ignore_ascii_case("of")),
+	);
+	INGREDIENTS
+		.iter()
+		.flat_map(|ingredient| {
+			ingredient
+				.aliases
+				.iter()
+				.map(move |alias| (alias.split(' ').count(), alias, ingredient))
+		})
+		.filter(|&(len, alias, _)| words.len() >= of + len && words[of..of + len].join(" ").eq_ignore_ascii_case(alias))
+		.max_by_key(|&(len, _, _)| len)
+		.map(|(len, _, ingredient)| (of + len, ingredient))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::conversion::registry::registry;
+
+	fn words(phrase: &str) -> Vec<String> {
+		phrase.split_whitespace().map(String::from).collect()
+	}
+
+	#[test]
+	fn matches_ingredients() {
+		let (len, flour) = match_ingredient(&words("of Flour and more")).unwrap();
+		assert_eq!((len, flour.name), (2, "flour"));
+		let (len, sugar) = match_ingredient(&words("brown sugar")).unwrap();
+		assert_eq!((len, sugar.name), (2, "brown sugar"));
+		assert!(match_ingredient(&words("of them")).is_none());
+		assert!(match_ingredient(&words("of")).is_none());
+		assert!(match_ingredient(&[]).is_none());
+	}
+
+	#[test]
+	fn converts_between_volume_and_mass() {
+		let (_, water) = match_ingredient(&words("water")).unwrap();
+		let liter = registry().get("liter").unwrap();
+		let kilogram = registry().get("kilogram").unwrap();
+		let weighed = water.convert(Quantity::of(2.0, liter), kilogram.dimensions);
+		assert_eq!(weighed.value_in(kilogram), Ok(2.0));
+		let measured = water.convert(Quantity::of(2.0, kilogram), liter.dimensions);
+		assert!((measured.value_in(liter).unwrap() - 2.0).abs() < 1e-9);
+		let meter = registry().get("meter").unwrap();
+		assert_eq!(
+			water.convert(Quantity::of(2.0, meter), kilogram.dimensions),
+			Quantity::of(2.0, meter)
+		);
+	}
+}
@@ -0,0 +1,142 @@
+// Discord formatting whose contents aren't something the author said in their own words,
+// every region is replaced by a single space so the words around it stay apart
+pub fn strip_markdown(msg: &str) -> String {
+	let msg = strip_code(msg);
+	let msg = strip_quotes(&msg);
+	let msg = strip_tags(&msg);
+	strip_links(&msg)
+}
+
+// ```code blocks```, ``inline code`` and `inline code`, unclosed backticks are shown as they are
+fn strip_code(msg: &str) -> String {
+	let mut stripped = String::with_capacity(msg.len());
+	let mut rest = msg;
+	while let Some(start) = rest.find('`') {
+		let fence = ["```", "``", "`"]
+			.iter()
+			.find(|fence| rest[start..].starts_with(*fence))
+			.unwrap_or(&"`");
+		let code = &rest[start + fence.len()..];
+		match code.find(fence) {
+			Some(end) => {
+				stripped.push_str(&rest[..start]);
+				stripped.push(' ');
+				rest = &code[end + fence.len()..];
+			}
+			None => {
+				stripped.push_str(&rest[..start + fence.len()]);
+				rest = code;
+			}
+		}
+	}
+	stripped.push_str(rest);
+	stripped
+}
+
+// "> " quotes a single line, ">>> " everything until the end of the message
+fn strip_quotes(msg: &str) -> String {
+	let mut lines = vec![];
+	for line in msg.lines() {
+		let trimmed = line.trim_start();
+		if trimmed.starts_with(">>> ") {
+			break;
+		}
+		if !trimmed.starts_with("> ") {
+			lines.push(line);
+		}
+	}
+	lines.join("\n")
+}
+
+// mentions, channels, roles, custom emoji, timestamps and slash commands: <@!123>, <#123>, <:name:123>, <t:123:R>
+fn strip_tags(msg: &str) -> String {
+	let mut stripped = String::with_capacity(msg.len());
+	let mut rest = msg;
+	while let Some(start) = rest.find('<') {
+		let tag = &rest[start + 1..];
+		let end = tag.find(|c: char| c == '>' || c.is_whitespace());
+		let is_tag = ["@", "#", ":", "a:", "t:", "/"]
+			.iter()
+			.any(|prefix| tag.starts_with(prefix));
+		match end {
+			Some(end) if is_tag && tag[end..].starts_with('>') => {
+				stripped.push_str(&rest[..start]);
+				stripped.push(' ');
+				rest = &tag[end + 1..];
+			}
+			_ => {
+				stripped.push_str(&rest[..start + 1]);
+				rest = tag;
+			}
+		}
+	}
+	stripped.push_str(rest);
+	stripped
+}
+
+// links like https://example.com/12-m/ or www.example.com/5km, the whitespace around them stays
+fn strip_links(msg: &str) -> String {
+	msg.split_inclusive(char::is_whitespace)
+		.map(|piece| {
+			let word = piece.trim_end_matches(char::is_whitespace);
+			if word.contains("://") || word.trim_start_matches('<').starts_with("www.") {
+				&piece[word.len()..]
+			} else {
+				piece
+			}
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn strips_code_blocks() {
+		assert_eq!(
+			strip_markdown("look:\n```rust\nlet x = 5 km;\n```\nit's 5 km away"),
+			"look:\n \nit's 5 km away"
+		);
+		assert_eq!(
+			strip_markdown("``5 km`` and `3 kg` but 2 miles"),
+			"  and   but 2 miles"
+		);
+		assert_eq!(strip_markdown("a lone ` and 5 km"), "a lone ` and 5 km");
+	}
+
+	#[test]
+	fn strips_quotes() {
+		assert_eq!(
+			strip_markdown("> it's 30 c\nno, it's 25 c"),
+			"no, it's 25 c"
+		);
+		assert_eq!(strip_markdown("I ran 5 km\n>>> 10 km\n20 km"), "I ran 5 km");
+		assert_eq!(strip_markdown("5 > 3 km"), "5 > 3 km");
+	}
+
+	#[test]
+	fn strips_urls() {
+		assert_eq!(
+			strip_markdown("see https://example.com/12-m/ for 12 m"),
+			"see  for 12 m"
+		);
+		assert_eq!(strip_markdown("<https://x.com/5km> www.y.com/3kg"), " ");
+		assert_eq!(
+			strip_markdown("ran 5 km\nhttps://x.com\tand 2 miles"),
+			"ran 5 km\n\tand 2 miles"
+		);
+	}
+
+	#[test]
+	fn strips_mentions_and_emoji() {
+		assert_eq!(
+			strip_markdown("<@!360433679111159808> is 6 ft"),
+			"  is 6 ft"
+		);
+		assert_eq!(strip_markdown("<#123> <@&456> <t:1634567890:R>"), "     ");
+		assert_eq!(strip_markdown("nice<:kg:123456789012>!"), "nice !");
+		assert_eq!(strip_markdown("<a:dance_5m:987654321>"), " ");
+		assert_eq!(strip_markdown("if 3 < 5 and 7 > 2"), "if 3 < 5 and 7 > 2");
+	}
+}
@@ -1,503 +1,856 @@
+mod calc;
+pub mod commands;
+mod confidence;
+mod error;
+mod ingredient;
+mod markdown;
+mod number;
+mod quantity;
+mod recipe;
+mod registry;
+mod spelled;
+mod system;
+
+pub use calc::calc_command;
+use confidence::Candidate;
+pub use confidence::Strictness;
+pub use error::ConversionError;
+use ingredient::Ingredient;
+pub use number::DecimalMark;
+use quantity::Quantity;
+pub use recipe::{scale_recipe, RECIPE_USAGE};
+use registry::{registry, Unit};
+pub use system::PreferredSystem;
+use system::UnitSystem;
+
+pub const COMMAND_PREFIX: &str = "!";
+
+// how a message should be converted, set per guild and user
+#[derive(Debug, Clone, Default)]
+pub struct ConversionOptions {
+	pub strictness: Strictness,
+	pub system: PreferredSystem,
+	pub decimal_mark: DecimalMark,
+}
+
 #[derive(Debug, PartialEq)]
-enum Units {
-	Miles(f64, String),
-	Feet(f64, String),
-	Inches(f64, String),
-	Kilometers(f64, String),
-	Meters(f64, String),
-	Centimeters(f64, String),
-	Pounds(f64, String),
-	Ounces(f64, String),
-	Kilograms(f64, String),
-	Grams(f64, String),
-	DegreesCelsius(f64, String),
-	DegreesFahrenheit(f64, String),
+struct Measurement {
+	value: f64,
+	unit: &'static Unit,
+	// written as several parts of a compound ("5 ft 11 in") and displayed the same way
+	compound: bool,
+	// the start of a range like "20–25 ℃", which ends at `value`
+	low: Option<f64>,
+	// marked as a rough value, "~3 miles"
+	approximate: bool,
+	// what is measured, "2 cups flour" can also be weighed
+	ingredient: Option<&'static Ingredient>,
+}
+
+impl Measurement {
+	#[cfg(test)]
+	fn new(value: f64, alias: &str) -> Result<Self, ConversionError> {
+		registry()
+			.lookup_all(alias)
+			.next()
+			.map(|unit| Measurement::from_unit(value, unit))
+			.ok_or_else(|| ConversionError::UnknownUnit(alias.to_string()))
+	}
+
+	fn from_unit(value: f64, unit: &'static Unit) -> Self {
+		Measurement {
+			value,
+			unit,
+			compound: false,
+			low: None,
+			approximate: false,
+			ingredient: None,
+		}
+	}
+
+	fn quantity(&self) -> Quantity {
+		Quantity::of(self.value, self.unit)
+	}
+
+	fn convert(&self) -> Option<Measurement> {
+		registry()
+			.counterpart(self.unit)
+			.map(|counterpart| self.convert_to(counterpart))
+	}
+
+	// converts into the units the audience is used to, None if they already are
+	fn convert_for(&self, system: PreferredSystem) -> Option<Measurement> {
+		// a volume of flour is more useful as its weight than as milliliters, a gallon of water isn't
+		if self.unit.dimension == "volume"
+			&& self
+				.ingredient
+				.is_some_and(|ingredient| ingredient.weighed_in(self.unit))
+			&& !system.includes(self.unit.system)
+		{
+			let mass = if system.includes(UnitSystem::Customary) {
+				"ounce"
+			} else {
+				"gram"
+			};
+			return registry().get(mass).map(|mass| self.convert_to(mass));
+		}
+		if system == PreferredSystem::Both {
+			return self.convert();
+		}
+		if system.includes(self.unit.system) {
+			return None;
+		}
+		let counterpart = registry().counterpart(self.unit)?;
+		if system.includes(counterpart.system) {
+			return Some(self.convert_to(counterpart));
+		}
+		// the unit of the preferred system that is closest in size, imperial gallons become US gallons,
+		// when the counterpart is the inverse it's closest to the counterpart, L/100km become imperial mpg
+		let reference = if counterpart.dimension == self.unit.dimension {
+			self.unit
+		} else {
+			counterpart
+		};
+		let distance = |unit: &Unit| (unit.factor / reference.factor).ln().abs();
+		let target = registry()
+			.units_of(&reference.dimension)
+			.filter(|unit| system.includes(unit.system))
+			.min_by(|a, b| distance(a).total_cmp(&distance(b)))
+			.unwrap_or(counterpart);
+		Some(self.convert_to(target))
+	}
+
+	// like convert_for, with a readable prefix unless the result is in the counterpart units.def names,
+	// "500 kcal" are 2092 kJ as the definitions say rather than 2.09 MJ
+	fn convert_readably(&self, system: PreferredSystem) -> Option<Measurement> {
+		let converted = self.convert_for(system)?;
+		if registry().counterpart(self.unit) == Some(converted.unit) {
+			Some(converted)
+		} else {
+			Some(converted.with_friendly_prefix())
+		}
+	}
+
+	// picks the SI prefix that keeps the value between 1 and 1000, "6096 m" becomes "6.10 km",
+	// values that already are in that range keep their unit so "180 cm" stays in centimeters
+	fn with_friendly_prefix(self) -> Measurement {
+		let family = match registry().family(self.unit) {
+			Some(family) => family,
+			None => return self,
+		};
+		if self.value == 0.0 || (1.0..1000.0).contains(&self.value.abs()) {
+			return self;
+		}
+		let base = family[0];
+		let mut prefixed: Vec<_> = family
+			.into_iter()
+			.filter(|unit| {
+				let thousands = (unit.factor / base.factor).log10() / 3.0;
+				(thousands - thousands.round()).abs() < 1e-9
+			})
+			.collect();
+		prefixed.sort_by(|a, b| a.factor.total_cmp(&b.factor));
+		let canonical = self.quantity().value.abs();
+		let target = prefixed
+			.iter()
+			.rev()
+			.find(|unit| canonical / unit.factor >= 1.0)
+			.or_else(|| prefixed.first());
+		match target {
+			Some(target) => self.convert_to(target),
+			None => self,
+		}
+	}
+
+	fn convert_to(&self, target: &'static Unit) -> Measurement {
+		let reciprocal = registry().reciprocal(self.unit, target);
+		let convert = |value| {
+			let quantity = Quantity::of(value, self.unit);
+			let quantity = match self.ingredient {
+				Some(ingredient) => ingredient.convert(quantity, target.dimensions),
+				None => quantity,
+			};
+			let quantity = if reciprocal {
+				quantity.power(-1)
+			} else {
+				quantity
+			};
+			target.to_unit(quantity.value)
+		};
+		let value = convert(self.value);
+		// inverting turns the low end of "30–40 mpg" into the high end
+		let (low, value) = match self.low.map(convert) {
+			Some(low) if low > value => (Some(value), low),
+			low => (low, value),
+		};
+		Measurement {
+			low,
+			approximate: self.approximate,
+			ingredient: self.ingredient,
+			..Measurement::from_unit(value, target)
+		}
+	}
+
+	// whether the measurement can be given in `unit`, an ingredient's volume also as its mass
+	// and fuel economy also as fuel consumption
+	fn converts_to(&self, unit: &Unit) -> bool {
+		unit.dimension == self.unit.dimension
+			|| registry().reciprocal(self.unit, unit)
+			|| self.ingredient.is_some() && ingredient::measures(self.unit) && ingredient::measures(unit)
+	}
+
+	fn destruct(&self) -> (f64, String) {
+		(self.value, self.unit.symbol.clone())
+	}
+}
+
+// reads the number at the start of `words` and how many words it takes,
+// a whole number followed by a fraction is a single number ("1 1/2" or "2 ¾")
+// and numbers may be spelled out ("two and a half")
+fn parse_value(words: &[String], decimal_mark: DecimalMark) -> Result<(f64, usize), ConversionError> {
+	let value = match number::parse_number(&words[0], decimal_mark) {
+		Ok(value) => value,
+		Err(why) => {
+			let longest = spelled::LONGEST_NUMBER.min(words.len());
+			return (1..=longest)
+				.rev()
+				.find_map(|len| spelled::parse_spelled(&words[..len]).map(|value| (value, len)))
+				.ok_or(why);
+		}
+	};
+	if words[0].parse::<i64>().is_err() {
+		return Ok((value, 1));
+	}
+	let fraction = match words
+		.get(1)
+		.filter(|word| number::is_fraction(word))
+		.map(|word| number::parse_number(word, decimal_mark))
+	{
+		Some(Ok(fraction)) if fraction >= 0.0 => fraction,
+		_ => return Ok((value, 1)),
+	};
+	if words[0].starts_with('-') {
+		Ok((value - fraction, 2))
+	} else {
+		Ok((value + fraction, 2))
+	}
+}
+
+// keeps the units of an ambiguous alias that the audience uses, a US audience means US gallons when it says "gallons"
+fn familiar_units(units: &mut Vec<&'static Unit>, system: PreferredSystem) {
+	if units.iter().any(|unit| system.includes(unit.system)) {
+		units.retain(|unit| system.includes(unit.system));
+	}
 }
 
-impl Units {
-	fn new(val: f64, unit: &str) -> Self {
-		match unit {
-			"km" | "kms" | "kilometer" | "kilometers" => Units::Kilometers(val, String::from("km")),
-			"m" | "ms" | "meter" | "meters" => Units::Meters(val, String::from("m")),
-			"cm" | "cms" | "centimeter" | "centimeters" => Units::Centimeters(val, String::from("cm")),
-			"mile" | "miles" => Units::Miles(val, String::from("miles")),
-			"feet" | "foot" | "ft" => Units::Feet(val, String::from("ft")),
-			"inches" | "inch" => Units::Inches(val, String::from("inches")),
-			"kg" | "kilogram" | "kilograms" => Units::Kilograms(val, String::from("kg")),
-			"g" | "gram" | "grams" => Units::Grams(val, String::from("grams")),
-			"lbs" | "pound" | "pounds" => Units::Pounds(val, String::from("lbs")),
-			"oz" | "ounces" | "ounce" => Units::Ounces(val, String::from("oz")),
-			"c" | "℃" | "celsius" => Units::DegreesCelsius(val, String::from("℃")),
-			"f" | "℉" | "fahrenheit" => Units::DegreesFahrenheit(val, String::from("℉")),
-			_ => panic!("Unknown type was passed into Units::new(), check your input"),
-		}
-	}
-
-	fn convert(&self) -> Units {
-		match self {
-			// length
-			// imperial
-			Units::Miles(val, _) => Units::Kilometers(val * 1.609344, String::from("km")),
-			Units::Feet(val, _) => Units::Meters(val * 0.3048, String::from("m")),
-			Units::Inches(val, _) => Units::Centimeters(val * 2.54, String::from("cm")),
-			// metric
-			Units::Kilometers(val, _) => Units::Miles(val * 0.6213712, String::from("miles")),
-			Units::Meters(val, _) => Units::Feet(val / 0.3048, String::from("ft")),
-			Units::Centimeters(val, _) => Units::Inches(val / 2.54, String::from("inches")),
-			// weight
-			// imperial
-			Units::Pounds(val, _) => Units::Kilograms(val * 0.4535924, String::from("kg")),
-			Units::Ounces(val, _) => Units::Grams(val * 28.34952, String::from("grams")),
-			// metric
-			Units::Kilograms(val, _) => Units::Pounds(val / 0.4535924, String::from("lbs")),
-			Units::Grams(val, _) => Units::Ounces(val / 28.34952, String::from("oz")),
-			// temperature
-			Units::DegreesCelsius(val, _) => Units::DegreesFahrenheit(val * 1.8 + 32_f64, String::from("℉")),
-			Units::DegreesFahrenheit(val, _) => Units::DegreesCelsius((val - 32_f64) / 1.8, String::from("℃")),
-		}
-	}
-
-	fn destruct_enum(unit: &Units) -> (f64, String) {
-		match unit {
-			Units::Miles(val, unit) => (*val, unit.to_string()),
-			Units::Feet(val, unit) => (*val, unit.to_string()),
-			Units::Inches(val, unit) => (*val, unit.to_string()),
-			// metric
-			Units::Kilometers(val, unit) => (*val, unit.to_string()),
-			Units::Meters(val, unit) => (*val, unit.to_string()),
-			Units::Centimeters(val, unit) => (*val, unit.to_string()),
-			// weight
-			// imperial
-			Units::Pounds(val, unit) => (*val, unit.to_string()),
-			Units::Ounces(val, unit) => (*val, unit.to_string()),
-			// metric
-			Units::Kilograms(val, unit) => (*val, unit.to_string()),
-			Units::Grams(val, unit) => (*val, unit.to_string()),
-			Units::DegreesCelsius(val, unit) => (*val, unit.to_string()),
-			Units::DegreesFahrenheit(val, unit) => (*val, unit.to_string()),
+// finds the longest alias at the start of `words`, so "fl oz" isn't read as an ounce of mass,
+// `part` also allows aliases that are only used inside a compound
+fn match_alias(words: &[String], part: bool) -> Option<(usize, Vec<&'static Unit>)> {
+	let longest = registry().longest_alias().min(words.len());
+	(1..=longest).rev().find_map(|len| {
+		let alias = words[..len].join(" ");
+		let units: Vec<_> = if part {
+			registry().lookup_part(&alias).collect()
+		} else {
+			registry().lookup_all(&alias).collect()
+		};
+		if units.is_empty() {
+			None
+		} else {
+			Some((len, units))
+		}
+	})
+}
+
+// continues a quantity like "5 ft" with the smaller units of its compound ("11 in"),
+// returns the merged measurement and how many of `words` it used
+fn parse_compound(
+	value: f64,
+	unit: &'static Unit,
+	words: &[String],
+	decimal_mark: DecimalMark,
+) -> (Measurement, usize) {
+	let mut measurement = Measurement::from_unit(value, unit);
+	let compound = match registry().compound(unit) {
+		Some(compound) => compound,
+		None => return (measurement, 0),
+	};
+	let mut position = compound
+		.iter()
+		.position(|part| part.name == unit.name)
+		.unwrap_or(0);
+	let mut canonical = unit.to_canonical(value);
+	let mut used = 0;
+	while let Some(Ok((value, count))) = (used < words.len()).then(|| parse_value(&words[used..], decimal_mark)) {
+		let next = match_alias(&words[used + count..], true).and_then(|(len, units)| {
+			units.into_iter().find_map(|unit| {
+				let index = compound.iter().position(|part| part.name == unit.name)?;
+				if index > position {
+					Some((len, unit, index))
+				} else {
+					None
+				}
+			})
+		});
+		match next {
+			Some((len, unit, index)) => {
+				canonical += unit.to_canonical(value);
+				measurement.unit = unit;
+				measurement.compound = true;
+				position = index;
+				used += count + len;
+			}
+			None => break,
+		}
+	}
+	// the parts are only added up in canonical units when there are several, so "150 lbs" stays 150
+	if used > 0 {
+		measurement.value = measurement.unit.to_unit(canonical);
+	}
+	(measurement, used)
+}
+
+// splits a word like "171cm", "-3°c" or "5'11\"" into its numbers and units
+fn split_number(word: &str) -> Vec<&str> {
+	// separators and the exponent only count between digits, so "10m/s" stays a unit
+	let between_digits = |i: usize, c: char| {
+		word[..i].ends_with(|c: char| c.is_ascii_digit())
+			&& word[i + c.len_utf8()..].starts_with(|c: char| c.is_ascii_digit())
+	};
+	let numeric = |i: usize, c: char| {
+		c.is_ascii_digit()
+			|| c == '.'
+			|| number::is_vulgar_fraction(c)
+			|| (i == 0 && (c == '-' || c == '+'))
+			|| (matches!(c, ',' | '/' | 'e' | 'E') && between_digits(i, c))
+	};
+	let mut parts = vec![];
+	let mut start = 0;
+	let mut previous = None;
+	for (i, c) in word.char_indices() {
+		let kind = numeric(i, c);
+		if previous.is_some_and(|previous| previous != kind) {
+			parts.push(&word[start..i]);
+			start = i;
 		}
+		previous = Some(kind);
+	}
+	parts.push(&word[start..]);
+	// the decimal mark only changes what a number means, not whether it is one
+	let is_number = |word: &str| number::parse_number(word, DecimalMark::default()).is_ok();
+	if is_number(word) || !is_number(parts[0]) {
+		return vec![word];
 	}
+	parts
 }
-const LIST_POSSIBLE: &[&str] = &[
-	"km",
-	"kms",
-	"kilometers",
-	"kilometer",
-	"m",
-	"meters",
-	"meter",
-	"cm",
-	"centimeters",
-	"centimeter",
-	"kg",
-	"kilograms",
-	"kilogram",
-	"grams",
-	"gram",
-	"mile",
-	"miles",
-	"ft",
-	"feet",
-	"foot",
-	"inches",
-	"inch",
-	"lbs",
-	"pounds",
-	"pound",
-	"oz",
-	"ounces",
-	"ounce",
-	"c",
-	"℃",
-	"celsius",
-	"f",
-	"℉",
-	"fahrenheit",
-];
-
-fn parse_input(msg: &str) -> Option<Vec<Units>> {
-	let mut has_convertibles = false;
-	let msg = msg.to_lowercase();
-	for elem in LIST_POSSIBLE {
-		if msg.contains(elem) {
-			has_convertibles = true;
+
+// strips trailing punctuation, but keeps the foot and inch marks of "6'" and "11\""
+fn trim_punctuation(mut word: &str) -> &str {
+	while let Some(c) = word.chars().last() {
+		if ![',', '.', '/', ';', ':', '|', '"', '\'', '\\'].contains(&c) {
+			break;
+		}
+		let rest = &word[..word.len() - c.len_utf8()];
+		if (c == '"' || c == '\'') && rest.ends_with(|c: char| c.is_ascii_digit()) {
 			break;
 		}
+		word = rest;
+	}
+	word
+}
+
+// splits a message into words without trailing punctuation, keeping compound units such as
+// "km / h" together as a single "km/h" word and separating numbers glued to their unit
+fn tokenize(msg: &str) -> Vec<String> {
+	tokenize_glued(msg)
+		.into_iter()
+		.map(|(word, _)| word)
+		.collect()
+}
+
+// like tokenize, but also tells whether each word was glued to the one before it ("171cm")
+fn tokenize_glued(msg: &str) -> Vec<(String, bool)> {
+	let mut joined = String::with_capacity(msg.len());
+	for c in msg.chars() {
+		if c == '/' {
+			joined.truncate(joined.trim_end().len());
+		}
+		if !(c.is_whitespace() && joined.ends_with('/')) {
+			joined.push(c);
+		}
+	}
+	let mut words = vec![];
+	for word in joined.split_whitespace() {
+		// the marker of "~3 miles" is a word of its own
+		let word = match word.strip_prefix(APPROXIMATE_MARKS) {
+			Some(rest) if !rest.is_empty() => {
+				words.push((word[..word.len() - rest.len()].to_string(), false));
+				rest
+			}
+			_ => word,
+		};
+		let parts = split_number(trim_punctuation(word));
+		// the inch mark is usually left out of heights like 5'11
+		let implied_inches = parts.len() > 2
+			&& matches!(parts[parts.len() - 2], "'" | "′")
+			&& parts[parts.len() - 1].parse::<f64>().is_ok();
+		words.extend(
+			parts
+				.into_iter()
+				.enumerate()
+				.map(|(i, part)| (part.to_string(), i > 0)),
+		);
+		if implied_inches {
+			words.push((String::from("\""), true));
+		}
+	}
+	words
+}
+
+const APPROXIMATE_MARKS: [char; 2] = ['~', '≈'];
+
+// words in front of a quantity that say it's a rough value
+const APPROXIMATE: &[&str] = &["~", "≈", "approx", "approximately", "ca", "circa"];
+
+// words between the ends of a range, "20-25 c" and "5 to 10 km"
+const RANGE_SEPARATORS: &[&str] = &["-", "–", "—", "to"];
+
+// a quantity read around the unit at `i`, "5 to 10 km", "~2 cups flour" or "5 ft 11 in"
+struct Reading {
+	measurements: Vec<Measurement>,
+	// the first word of the quantity, its number or the low end of its range
+	first: usize,
+	spelled: bool,
+	// the words of the unit
+	unit: usize,
+	unit_len: usize,
+	// the end of the quantity, in front of its ingredient
+	end: usize,
+	// where the next quantity can start, after the ingredient
+	next: usize,
+}
+
+// reads the quantity whose unit starts at `msg[i]`, `words` are the same words in lowercase,
+// `convertible` leaves out units without a counterpart, which are only converted on request
+fn read_quantity(
+	msg: &[String],
+	words: &[String],
+	mut i: usize,
+	options: &ConversionOptions,
+	convertible: bool,
+) -> Option<Reading> {
+	// "1 1/2 cups" takes both words before the unit
+	let number = if i >= 2 && number::is_fraction(&msg[i - 1]) {
+		parse_value(&msg[i - 2..i], options.decimal_mark)
+			.ok()
+			.filter(|&(_, used)| used == 2)
+			.map(|(value, _)| (value, i - 2))
+	} else {
+		None
 	}
-	if !has_convertibles {
+	.or_else(|| {
+		number::parse_number(&msg[i - 1], options.decimal_mark)
+			.ok()
+			.map(|value| (value, i - 1))
+	})
+	.map(|(value, number)| (value, number, false))
+	// "five miles" or "two and a half feet", the longest phrase in front of the unit wins
+	.or_else(|| {
+		(i.saturating_sub(spelled::LONGEST_NUMBER)..i)
+			.find_map(|start| spelled::parse_spelled(&msg[start..i]).map(|value| (value, start, true)))
+	});
+	let (value, number, spelled) = number?;
+	let (len, mut units) = match_alias(&msg[i..], false)?;
+	// units without a counterpart are only converted on request, "5 min" needs no reply
+	if convertible {
+		units.retain(|unit| unit.counterpart.is_some());
+	}
+	// "1 c flour" isn't a temperature
+	if ingredient::match_ingredient(&words[i + len..]).is_some() {
+		units.retain(|unit| unit.offset == 0.0);
+	}
+	if units.is_empty() {
 		return None;
 	}
+	let unit = i;
+	i += len;
+	familiar_units(&mut units, options.system);
+	// "20-25 c" and "5 to 10 km" start at the number in front of the separator
+	let low = number
+		.checked_sub(2)
+		.filter(|&low| RANGE_SEPARATORS.contains(&words[low + 1].as_str()))
+		.and_then(|low| {
+			parse_value(&msg[low..=low], options.decimal_mark)
+				.ok()
+				.map(|(value, _)| (value, low))
+		});
+	let first = low.map_or(number, |(_, low)| low);
+	let approximate = first
+		.checked_sub(1)
+		.is_some_and(|i| APPROXIMATE.contains(&words[i].as_str()));
+	let mut measurements = match units[..] {
+		// the ends of a range are never compounds
+		[unit] if low.is_none() => {
+			let (measurement, used) = parse_compound(value, unit, &msg[i..], options.decimal_mark);
+			i += used;
+			vec![measurement]
+		}
+		_ => units
+			.into_iter()
+			.map(|unit| Measurement::from_unit(value, unit))
+			.collect(),
+	};
+	let end = i;
+	// "2 cups flour" or "250 g of sugar", but not "2 gallons of water"
+	let ingredient = ingredient::match_ingredient(&words[i..]).filter(|(_, ingredient)| {
+		measurements.iter().all(|measurement| {
+			measurement.unit.dimension == "mass"
+				|| measurement.unit.dimension == "volume" && ingredient.weighed_in(measurement.unit)
+		})
+	});
+	if let Some((len, _)) = ingredient {
+		i += len;
+	}
+	for measurement in &mut measurements {
+		// "-5 – -10 ℃" is the same range as "-10 – -5 ℃", and converted that way
+		if let Some((low, _)) = low {
+			measurement.low = Some(low.min(measurement.value));
+			measurement.value = low.max(measurement.value);
+		}
+		measurement.approximate = approximate;
+		measurement.ingredient = ingredient.map(|(_, ingredient)| ingredient);
+	}
+	Some(Reading {
+		measurements,
+		first,
+		spelled,
+		unit,
+		unit_len: len,
+		end,
+		next: i,
+	})
+}
+
+fn parse_input(msg: &str, options: &ConversionOptions) -> Result<Vec<Measurement>, ConversionError> {
+	let lowercase = msg.to_lowercase();
+	if !(msg.contains(|c: char| c.is_ascii_digit() || number::is_vulgar_fraction(c))
+		|| lowercase
+			.split(|c: char| !c.is_alphabetic())
+			.any(spelled::is_number_word))
+		|| !registry()
+			.aliases()
+			.any(|alias| msg.contains(alias) || lowercase.contains(alias))
+	{
+		return Err(ConversionError::NothingToConvert);
+	}
 
-	let msg: Vec<_> = msg.split_ascii_whitespace().collect();
+	// units are matched as written so "Cal" and "cal" can differ, everything else reads lowercase words
+	let (msg, glued): (Vec<_>, Vec<_>) = tokenize_glued(msg).into_iter().unzip();
+	let words: Vec<_> = msg.iter().map(|word| word.to_lowercase()).collect();
 	let mut values_vec = vec![];
-	for i in 0..msg.len() {
-		let word = msg[i].trim_end_matches(&[',', '.', '/', ';', ':', '|', '"', '\'', '\\'][..]);
-		if LIST_POSSIBLE.contains(&word) {
-			if let Ok(val) = msg[i - 1].parse::<f64>() {
-				values_vec.push(Units::new(val, word));
+	let mut i = 1;
+	while i < msg.len() {
+		let reading = match read_quantity(&msg, &words, i, options, true) {
+			Some(reading) => reading,
+			None => {
+				i += 1;
+				continue;
+			}
+		};
+		i = reading.next;
+		let mut measurements = reading.measurements;
+		let candidate = Candidate {
+			words: &words,
+			number: reading.first,
+			range: measurements[0].low.is_some(),
+			spelled: reading.spelled,
+			end: reading.end,
+			glued: glued[reading.unit],
+			ambiguous: registry().is_ambiguous(&msg[reading.unit..reading.unit + reading.unit_len].join(" ")),
+			compound: measurements[0].compound,
+		};
+		if options.strictness.accepts(candidate.confidence()) {
+			if candidate.describes_difference() {
+				for measurement in &mut measurements {
+					if let Some(difference) = registry().difference(measurement.unit) {
+						measurement.unit = difference;
+					}
+				}
 			}
+			values_vec.append(&mut measurements);
 		}
 	}
 	if !values_vec.is_empty() {
-		Some(values_vec)
+		Ok(values_vec)
 	} else {
-		None
+		Err(ConversionError::NothingToConvert)
 	}
 }
 
-fn assemble_response(values_vec: &[Units]) -> String {
-	let mut response = String::new();
-	for v in values_vec {
-		let (value, unit) = Units::destruct_enum(v);
-		let (converted_value, converted_unit) = Units::destruct_enum(&v.convert());
-		if converted_value.abs() < 1.0 {
-			response.push_str(&format!(
-				"{} {} is {} {}\n",
-				value, unit, converted_value, converted_unit
-			));
-		} else {
-			response.push_str(&format!(
-				"{} {} is {:.2} {}\n",
-				value, unit, converted_value, converted_unit
-			));
+// shows a value in the smallest unit of a compound the way people write it, "5 ft 10.9 in"
+fn format_compound(value: f64, unit: &Unit) -> Option<String> {
+	let compound = registry().compound(unit)?;
+	let (smallest, larger) = compound.split_last()?;
+	if smallest.name != unit.name || value.abs() < larger.last()?.factor / unit.factor {
+		return None;
+	}
+	let mut rest = (value.abs() * 10.0).round() / 10.0;
+	let mut parts = vec![];
+	for part in larger {
+		let size = part.factor / unit.factor;
+		let count = (rest / size + 1e-9).floor();
+		rest = ((rest - count * size) * 10.0).round() / 10.0;
+		if count > 0.0 {
+			parts.push(format!("{} {}", count, part.symbol));
 		}
 	}
-	response
+	if rest > 0.0 || parts.is_empty() {
+		parts.push(format!("{} {}", rest, unit.symbol));
+	}
+	let sign = if value < 0.0 { "-" } else { "" };
+	Some(format!("{}{}", sign, parts.join(" ")))
 }
 
-pub fn respond_to_msg(msg: &str) -> Option<String> {
-	parse_input(msg).map(|units| assemble_response(&units))
+fn format_number(value: f64) -> String {
+	if value.abs() < 1.0 {
+		value.to_string()
+	} else {
+		format!("{:.2}", value)
+	}
 }
 
-#[cfg(test)]
-mod tests {
-	use super::*;
-	use float_cmp::{approx_eq, F64Margin};
+fn format_value(value: f64, unit: &str) -> String {
+	format!("{} {}", format_number(value), unit)
+}
 
-	#[test]
-	fn unit_conversion() {
-		if let Units::Meters(val_left, _) = Units::Meters(3.048, String::from("m")) {
-			if let Units::Meters(val_right, _) = Units::Feet(10.0, String::from("ft")).convert() {
-				assert!(
-					approx_eq!(
-						f64,
-						val_left,
-						val_right,
-						F64Margin {
-							epsilon: 0.001,
-							ulps: 2
-						}
-					),
-					"\nleft != right\n{} != {}",
-					val_left,
-					val_right
-				);
-			} else {
-				panic!("FAILED TO ASSIGN")
-			}
-		} else {
-			panic!("FAILED TO ASSIGN")
-		}
-		if let Units::Feet(val_left, _) = Units::Feet(32.80839895, String::from("ft")) {
-			if let Units::Feet(val_right, _) = Units::Meters(10.0, String::from("m")).convert() {
-				assert!(
-					approx_eq!(
-						f64,
-						val_left,
-						val_right,
-						F64Margin {
-							epsilon: 0.001,
-							ulps: 2
-						}
-					),
-					"\nleft != right\n{} != {}",
-					val_left,
-					val_right
-				);
-			} else {
-				panic!("FAILED TO ASSIGN")
-			}
-		} else {
-			panic!("FAILED TO ASSIGN")
-		}
-		if let Units::Kilograms(val_left, _) = Units::Kilograms(4.535924, String::from("kg")) {
-			if let Units::Kilograms(val_right, _) = Units::Pounds(10.0, String::from("lbs")).convert() {
-				assert!(
-					approx_eq!(
-						f64,
-						val_left,
-						val_right,
-						F64Margin {
-							epsilon: 0.001,
-							ulps: 2
-						}
-					),
-					"\nleft != right\n{} != {}",
-					val_left,
-					val_right
-				);
-			} else {
-				panic!("FAILED TO ASSIGN")
-			}
-		} else {
-			panic!("FAILED TO ASSIGN")
-		}
-		if let Units::Pounds(val_left, _) = Units::Pounds(22.04623, String::from("lbs")) {
-			if let Units::Pounds(val_right, _) = Units::Kilograms(10.0, String::from("kg")).convert() {
-				assert!(
-					approx_eq!(
-						f64,
-						val_left,
-						val_right,
-						F64Margin {
-							epsilon: 0.001,
-							ulps: 2
-						}
-					),
-					"\nleft != right\n{} != {}",
-					val_left,
-					val_right
-				);
-			} else {
-				panic!("FAILED TO ASSIGN")
-			}
-		} else {
-			panic!("FAILED TO ASSIGN")
-		}
-		if let Units::Ounces(val_left, _) = Units::Ounces(0.3527396, String::from("oz")) {
-			if let Units::Ounces(val_right, _) = Units::Grams(10.0, String::from("g")).convert() {
-				assert!(
-					approx_eq!(
-						f64,
-						val_left,
-						val_right,
-						F64Margin {
-							epsilon: 0.001,
-							ulps: 2
-						}
-					),
-					"\nleft != right\n{} != {}",
-					val_left,
-					val_right
-				);
-			} else {
-				panic!("FAILED TO ASSIGN")
-			}
-		} else {
-			panic!("FAILED TO ASSIGN")
-		}
-		if let Units::Grams(val_left, _) = Units::Grams(283.4952, String::from("g")) {
-			if let Units::Grams(val_right, _) = Units::Ounces(10.0, String::from("oz")).convert() {
-				assert!(
-					approx_eq!(
-						f64,
-						val_left,
-						val_right,
-						F64Margin {
-							epsilon: 0.001,
-							ulps: 2
-						}
-					),
-					"\nleft != right\n{} != {}",
-					val_left,
-					val_right
-				);
-			} else {
-				panic!("FAILED TO ASSIGN")
-			}
+// writes a measurement with `number` for its values, the ends of a range share their unit ("20–25 ℃")
+// unless `compound` shows them as compounds of their own ("5 ft 6 in–5 ft 10 in")
+fn format_measurement(measurement: &Measurement, compound: bool, number: impl Fn(f64) -> String) -> String {
+	let as_compound = |value| {
+		if compound {
+			format_compound(value, measurement.unit)
 		} else {
-			panic!("FAILED TO ASSIGN")
-		}
-		if let Units::Miles(val_left, _) = Units::Miles(10.0, String::from("miles")) {
-			if let Units::Miles(val_right, _) = Units::Kilometers(16.09344, String::from("km")).convert() {
-				assert!(
-					approx_eq!(
-						f64,
-						val_left,
-						val_right,
-						F64Margin {
-							epsilon: 0.001,
-							ulps: 2
-						}
-					),
-					"\nleft != right\n{} != {}",
-					val_left,
-					val_right
-				);
-			} else {
-				panic!("FAILED TO ASSIGN")
-			}
-		} else {
-			panic!("FAILED TO ASSIGN")
-		}
-		if let Units::Kilometers(val_left, _) = Units::Kilometers(10.0, String::from("km")) {
-			if let Units::Kilometers(val_right, _) = Units::Miles(6.213712, String::from("miles")).convert() {
-				assert!(
-					approx_eq!(
-						f64,
-						val_left,
-						val_right,
-						F64Margin {
-							epsilon: 0.001,
-							ulps: 2
-						}
-					),
-					"\nleft != right\n{} != {}",
-					val_left,
-					val_right
-				);
-			} else {
-				panic!("FAILED TO ASSIGN")
+			None
+		}
+	};
+	let (value, unit) = measurement.destruct();
+	let text = match measurement.low {
+		Some(low) => match (as_compound(low), as_compound(value)) {
+			(Some(low), Some(high)) => format!("{}–{}", low, high),
+			_ => format!("{}–{} {}", number(low), number(value), unit),
+		},
+		None => as_compound(value).unwrap_or_else(|| format!("{} {}", number(value), unit)),
+	};
+	if measurement.approximate {
+		format!("~{}", text)
+	} else {
+		text
+	}
+}
+
+// the quantity as the user wrote it
+fn format_source(measurement: &Measurement) -> String {
+	let source = format_measurement(measurement, measurement.compound, |value| value.to_string());
+	match measurement.ingredient {
+		Some(ingredient) => format!("{} {}", source, ingredient.name),
+		None => source,
+	}
+}
+
+fn assemble_response(values_vec: &[Measurement], options: &ConversionOptions) -> String {
+	let mut response = String::new();
+	for v in values_vec {
+		let converted = match v.convert_readably(options.system) {
+			// "0 mpg" has no fuel consumption
+			Some(converted) if converted.value.is_finite() && converted.low.unwrap_or_default().is_finite() => {
+				converted
 			}
-		} else {
-			panic!("FAILED TO ASSIGN")
-		}
-		if let Units::DegreesCelsius(val_left, _) = Units::DegreesCelsius(10.0, String::from("℃")) {
-			if let Units::DegreesCelsius(val_right, _) = Units::DegreesFahrenheit(50.0, String::from("℉")).convert() {
-				assert!(
-					approx_eq!(
-						f64,
-						val_left,
-						val_right,
-						F64Margin {
-							epsilon: 0.001,
-							ulps: 2
-						}
-					),
-					"\nleft != right\n{} != {}",
-					val_left,
-					val_right
-				);
-			} else {
-				panic!("FAILED TO ASSIGN")
+			_ => continue,
+		};
+		let target = format_measurement(&converted, true, format_number);
+		response.push_str(&format!("{} is {}\n", format_source(v), target));
+	}
+	response
+}
+
+// commands are answered by the command itself, "!convert 5 km to miles" isn't converted a second time
+pub fn is_command(msg: &str) -> bool {
+	msg.trim_start().starts_with(COMMAND_PREFIX)
+}
+
+pub fn respond_to_msg(msg: &str, options: &ConversionOptions) -> Result<String, ConversionError> {
+	let units = parse_input(&markdown::strip_markdown(msg), options)?;
+	match assemble_response(&units, options) {
+		response if response.is_empty() => Err(ConversionError::NothingToConvert),
+		response => Ok(response),
+	}
+}
+
+const CONVERT_USAGE: &str = "`!convert <value> <unit> to <unit>[, <unit>...]`, e.g. `!convert 5 km to miles, ft`";
+
+// handles the arguments of `!convert`, "5 km to miles, yards" or "5 ft 11 in to cm"
+pub fn convert_command(args: &str, options: &ConversionOptions) -> Result<String, ConversionError> {
+	let words = tokenize(args);
+	let to = words
+		.iter()
+		.position(|word| word.eq_ignore_ascii_case("to"));
+	let (source, targets) = match to {
+		Some(to) if to >= 2 => (&words[..to], &words[to + 1..]),
+		_ => return Err(ConversionError::Usage(CONVERT_USAGE)),
+	};
+	let (value, count) = parse_value(source, options.decimal_mark)?;
+	// nobody writes "5 in to cm" meaning anything but inches
+	let (measurement, quantity) = match match_alias(&source[count..], true) {
+		Some((len, units)) => {
+			let (mut measurement, mut used) = parse_compound(
+				value,
+				units[0],
+				&source[count + len..],
+				options.decimal_mark,
+			);
+			if let Some((ingredient_len, ingredient)) = ingredient::match_ingredient(&source[count + len + used..])
+				.filter(|_| ingredient::measures(measurement.unit))
+			{
+				measurement.ingredient = Some(ingredient);
+				used += ingredient_len;
 			}
-		} else {
-			panic!("FAILED TO ASSIGN")
-		}
-		if let Units::DegreesCelsius(val_left, _) = Units::DegreesCelsius(0.0, String::from("℃")) {
-			if let Units::DegreesCelsius(val_right, _) = Units::DegreesFahrenheit(32.0, String::from("℉")).convert() {
-				assert!(
-					approx_eq!(
-						f64,
-						val_left,
-						val_right,
-						F64Margin {
-							epsilon: 0.001,
-							ulps: 2
-						}
-					),
-					"\nleft != right\n{} != {}",
-					val_left,
-					val_right
-				);
-			} else {
-				panic!("FAILED TO ASSIGN")
+			if count + len + used < source.len() {
+				return Err(ConversionError::UnexpectedInput(
+					source[count + len + used..].join(" "),
+				));
 			}
-		} else {
-			panic!("FAILED TO ASSIGN")
+			let quantity = measurement.quantity();
+			(Some(measurement), quantity)
 		}
-		if let Units::DegreesCelsius(val_left, _) = Units::DegreesCelsius(-40.0, String::from("℃")) {
-			if let Units::DegreesCelsius(val_right, _) = Units::DegreesFahrenheit(-40.0, String::from("℉")).convert()
-			{
-				assert!(
-					approx_eq!(
-						f64,
-						val_left,
-						val_right,
-						F64Margin {
-							epsilon: 0.001,
-							ulps: 2
-						}
-					),
-					"\nleft != right\n{} != {}",
-					val_left,
-					val_right
-				);
-			} else {
-				panic!("FAILED TO ASSIGN")
-			}
-		} else {
-			panic!("FAILED TO ASSIGN")
+		// units without a name of their own, "10 ft/min"
+		None => {
+			let (unit, _) = calc::unit_expression(&source[count..].join(" "), options)?;
+			(None, Quantity::number(value).multiply(unit))
 		}
-		if let Units::DegreesFahrenheit(val_left, _) = Units::DegreesFahrenheit(15.0, String::from("℃")) {
-			if let Units::DegreesFahrenheit(val_right, _) =
-				Units::DegreesCelsius(-9.444444, String::from("℉")).convert()
-			{
-				assert!(
-					approx_eq!(
-						f64,
-						val_left,
-						val_right,
-						F64Margin {
-							epsilon: 0.001,
-							ulps: 2
-						}
-					),
-					"\nleft != right\n{} != {}",
-					val_left,
-					val_right
-				);
-			} else {
-				panic!("FAILED TO ASSIGN")
-			}
-		} else {
-			panic!("FAILED TO ASSIGN")
+	};
+
+	let mut converted = vec![];
+	let mut i = 0;
+	while i < targets.len() {
+		if targets[i].eq_ignore_ascii_case("and") {
+			i += 1;
+			continue;
 		}
-		if let Units::DegreesFahrenheit(val_left, _) = Units::DegreesFahrenheit(0.0, String::from("℃")) {
-			if let Units::DegreesFahrenheit(val_right, _) =
-				Units::DegreesCelsius(-17.77778, String::from("℉")).convert()
-			{
-				assert!(
-					approx_eq!(
-						f64,
-						val_left,
-						val_right,
-						F64Margin {
-							epsilon: 0.001,
-							ulps: 2
-						}
-					),
-					"\nleft != right\n{} != {}",
-					val_left,
-					val_right
-				);
-			} else {
-				panic!("FAILED TO ASSIGN")
+		let (len, units) = match match_alias(&targets[i..], true) {
+			Some(units) => units,
+			None => {
+				// "m/s" has a name but "ft/min" is worked out from its parts
+				let (unit, _) = calc::unit_expression(&targets[i], options)?;
+				if let Some(measurement) = measurement.as_ref().filter(|m| m.unit.offset != 0.0) {
+					return Err(ConversionError::AbsoluteTemperature(measurement.unit));
+				}
+				match quantity.value_per(unit)? {
+					value if value.is_finite() => converted.push(format_value(value, &targets[i])),
+					_ => return Err(ConversionError::Overflow),
+				}
+				i += 1;
+				continue;
 			}
-		} else {
-			panic!("FAILED TO ASSIGN")
-		}
-		if let Units::DegreesFahrenheit(val_left, _) = Units::DegreesFahrenheit(-40.0, String::from("℃")) {
-			if let Units::DegreesFahrenheit(val_right, _) = Units::DegreesCelsius(-40.0, String::from("℉")).convert()
-			{
-				assert!(
-					approx_eq!(
-						f64,
-						val_left,
-						val_right,
-						F64Margin {
-							epsilon: 0.001,
-							ulps: 2
-						}
-					),
-					"\nleft != right\n{} != {}",
-					val_left,
-					val_right
-				);
-			} else {
-				panic!("FAILED TO ASSIGN")
+		};
+		for unit in units {
+			let value = match &measurement {
+				// temperatures and their differences share dimensions but aren't the same thing
+				Some(measurement) if !measurement.converts_to(unit) => {
+					return Err(ConversionError::IncompatibleUnits(measurement.unit, unit));
+				}
+				Some(measurement) => measurement.convert_to(unit).value,
+				None if unit.offset != 0.0 => return Err(ConversionError::AbsoluteTemperature(unit)),
+				None => quantity.value_in(unit)?,
+			};
+			if !value.is_finite() {
+				// only "0 mpg" divides by zero, anything else is too large
+				let inverted = measurement.as_ref().is_some_and(|measurement| {
+					measurement.value == 0.0 && registry().reciprocal(measurement.unit, unit)
+				});
+				return Err(if inverted {
+					ConversionError::DivisionByZero
+				} else {
+					ConversionError::Overflow
+				});
 			}
-		} else {
-			panic!("FAILED TO ASSIGN")
+			converted.push(format_value(value, &unit.symbol));
+		}
+		i += len;
+	}
+	if converted.is_empty() {
+		return Err(ConversionError::Usage(CONVERT_USAGE));
+	}
+	let source = match &measurement {
+		Some(measurement) => format_source(measurement),
+		None => format!("{} {}", value, source[count..].join(" ")),
+	};
+	Ok(format!("{} is {}", source, converted.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use float_cmp::{approx_eq, F64Margin};
+	use proptest::prelude::*;
+
+	fn measurement(value: f64, alias: &str) -> Measurement {
+		Measurement::new(value, alias).unwrap()
+	}
+
+	fn with_ingredient(measurement: Measurement, name: &str) -> Measurement {
+		Measurement {
+			ingredient: ingredient::match_ingredient(&[name.to_string()]).map(|(_, ingredient)| ingredient),
+			..measurement
 		}
 	}
 
+	fn assert_converts(from: Measurement, expected: Measurement) {
+		assert_approx_eq(&from.convert().unwrap(), &expected);
+	}
+
+	fn assert_approx_eq(converted: &Measurement, expected: &Measurement) {
+		assert_eq!(converted.unit, expected.unit);
+		assert_eq!(converted.compound, expected.compound);
+		assert!(
+			approx_eq!(
+				f64,
+				expected.value,
+				converted.value,
+				F64Margin {
+					epsilon: 0.001,
+					ulps: 2
+				}
+			),
+			"\nleft != right\n{} != {}",
+			expected.value,
+			converted.value
+		);
+	}
+
+	#[test]
+	fn unit_conversion() {
+		assert_converts(measurement(10.0, "ft"), measurement(3.048, "m"));
+		assert_converts(measurement(10.0, "m"), measurement(32.80839895, "ft"));
+		assert_converts(measurement(10.0, "lbs"), measurement(4.535924, "kg"));
+		assert_converts(measurement(10.0, "kg"), measurement(22.04623, "lbs"));
+		assert_converts(measurement(10.0, "g"), measurement(0.3527396, "oz"));
+		assert_converts(measurement(10.0, "oz"), measurement(283.4952, "g"));
+		assert_converts(measurement(16.09344, "km"), measurement(10.0, "miles"));
+		assert_converts(measurement(6.213712, "miles"), measurement(10.0, "km"));
+		assert_converts(measurement(50.0, "℉"), measurement(10.0, "℃"));
+		assert_converts(measurement(32.0, "℉"), measurement(0.0, "℃"));
+		assert_converts(measurement(-40.0, "℉"), measurement(-40.0, "℃"));
+		assert_converts(measurement(-9.444444, "℃"), measurement(15.0, "℉"));
+		assert_converts(measurement(-17.77778, "℃"), measurement(0.0, "℉"));
+		assert_converts(measurement(-40.0, "℃"), measurement(-40.0, "℉"));
+	}
+
 	#[test]
 	fn parsing_msg_single_unit() {
 		let msg = "Hello, I am 171 cm tall";
 		assert_eq!(
-			parse_input(msg),
-			Some(vec![Units::Centimeters(171.0, String::from("cm"))])
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![measurement(171.0, "cm")])
 		);
 	}
 
@@ -505,25 +858,25 @@ mod tests {
 	fn parsing_msg_multiple_units() {
 		let msg = "Hello, I am 171 cm tall and weigh 140 pounds";
 		assert_eq!(
-			parse_input(msg),
-			Some(vec![
-				Units::Centimeters(171.0, String::from("cm")),
-				Units::Pounds(140.0, String::from("lbs"))
-			])
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![measurement(171.0, "cm"), measurement(140.0, "lbs")])
 		);
 	}
 
 	#[test]
 	fn parsing_msg_malformed_single_unit() {
 		let msg = "Hello, I am none cm tall";
-		assert_eq!(parse_input(msg), None);
+		assert_eq!(
+			parse_input(msg, &ConversionOptions::default()),
+			Err(ConversionError::NothingToConvert)
+		);
 	}
 	#[test]
 	fn parsing_msg_malformed_unit_plus_additional_correct_unit() {
 		let msg = "Hello, I am none cm tall and weigh 140 pounds";
 		assert_eq!(
-			parse_input(msg),
-			Some(vec![Units::Pounds(140.0, String::from("lbs"))])
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![measurement(140.0, "lbs")])
 		);
 	}
 
@@ -531,11 +884,8 @@ mod tests {
 	fn parsing_msg_multiple_malformed_units_multiple_correct_units() {
 		let msg = "Hello, I am none cm tall and weigh 140 pounds and my city is 343 kms in area and my cat's name is little Foot";
 		assert_eq!(
-			parse_input(msg),
-			Some(vec![
-				Units::Pounds(140.0, String::from("lbs")),
-				Units::Kilometers(343.0, String::from("km"))
-			])
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![measurement(140.0, "lbs"), measurement(343.0, "km")])
 		);
 	}
 
@@ -543,11 +893,8 @@ mod tests {
 	fn parsing_msg_strip_punctuation_correct_units() {
 		let msg = "Hello, I am none cm tall and weigh 140 pounds. My city is 343 kms in area and my cat's name is little Foot";
 		assert_eq!(
-			parse_input(msg),
-			Some(vec![
-				Units::Pounds(140.0, String::from("lbs")),
-				Units::Kilometers(343.0, String::from("km"))
-			])
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![measurement(140.0, "lbs"), measurement(343.0, "km")])
 		);
 	}
 
@@ -555,11 +902,8 @@ mod tests {
 	fn parsing_msg_strip_multiple_punctuation_correct_units() {
 		let msg = "Hello, I am none cm tall and weigh 140 pounds,|.,;. My city is 343 kms.,; in area and my cat's name is little Foot";
 		assert_eq!(
-			parse_input(msg),
-			Some(vec![
-				Units::Pounds(140.0, String::from("lbs")),
-				Units::Kilometers(343.0, String::from("km"))
-			])
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![measurement(140.0, "lbs"), measurement(343.0, "km")])
 		);
 	}
 
@@ -567,44 +911,1186 @@ mod tests {
 	fn parsing_msg_parse_floats() {
 		let msg = "Maddy-hops is exactly 0.00171 kilometers tall";
 		assert_eq!(
-			parse_input(msg),
-			Some(vec![Units::Kilometers(0.00171, String::from("km"))])
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![measurement(0.00171, "km")])
+		);
+	}
+
+	#[test]
+	fn parsing_msg_milliseconds() {
+		for msg in ["my ping is 30 ms", "latency was 200 ms, 5 cms"] {
+			assert_eq!(
+				parse_input(msg, &ConversionOptions::default()),
+				Err(ConversionError::NothingToConvert)
+			);
+		}
+	}
+
+	#[test]
+	fn registry_definitions() {
+		let registry = registry::Registry::parse(include_str!("units.def")).unwrap();
+		assert_eq!(
+			registry.lookup_all("kilometers").next().unwrap().name,
+			"kilometer"
+		);
+		assert_eq!(
+			registry.lookup_all("℉").next().unwrap().dimension,
+			"temperature"
 		);
+		assert!(registry.lookup_all("parsecs").next().is_none());
+		assert!(registry::Registry::parse("length | meter | m | metric | 1 | 0 | meter | m").is_ok());
+		assert!(registry::Registry::parse("length | meter | m | metric | 1 | 0 | foot | m").is_err());
+		assert!(registry::Registry::parse("length | meter | m | metric | one | 0 | meter | m").is_err());
+		assert!(registry::Registry::parse("distance | meter | m | metric | 1 | 0 | meter | m").is_err());
+		assert!(registry::Registry::parse("reciprocal | length, area").is_err());
+		assert!(registry::Registry::parse("reciprocal | fuel economy, fuel consumption").is_ok());
+		assert!(registry::Registry::parse(
+			"area | square meter | m² | metric | 1 | 0 | square meter | m²\nlength | meter | m | metric | 1 | 0 | square meter | m"
+		)
+		.is_err());
+		assert!(registry::Registry::parse("length | meter | m | nautical | 1 | 0 | meter | m").is_err());
+		assert!(registry::Registry::parse(
+			"length | meter | m | metric | 1 | 0 | meter | m\nmass | gram | g | metric | 0.001 | 0 | gram | g, m"
+		)
+		.is_err());
 	}
 
 	#[test]
 	fn destructing_units() {
-		let unit = Units::Feet(300.0, String::from("ft"));
-		assert_eq!(Units::destruct_enum(&unit,), (300.0, String::from("ft")))
+		let unit = measurement(300.0, "ft");
+		assert_eq!(unit.destruct(), (300.0, String::from("ft")))
 	}
 
 	#[test]
 	fn assemble_response_single_unit() {
 		let msg = "Maddy-hops is exactly 0.00171 kilometers tall";
-		let units_vec = parse_input(msg).unwrap();
+		let units_vec = parse_input(msg, &ConversionOptions::default()).unwrap();
 		assert_eq!(
-			"0.00171 km is 0.001062544752 miles\n".to_string(),
-			assemble_response(&units_vec)
+			"0.00171 km is 0.001062544738725841 miles\n".to_string(),
+			assemble_response(&units_vec, &ConversionOptions::default())
 		);
 	}
 
 	#[test]
 	fn assemble_response_multiple_units() {
 		let msg = "Maddy-hops is exactly 0.00171 kilometers tall and weighs 140 pounds.";
-		let units_vec = parse_input(msg).unwrap();
+		let units_vec = parse_input(msg, &ConversionOptions::default()).unwrap();
 		assert_eq!(
-			"0.00171 km is 0.001062544752 miles\n140 lbs is 63.50 kg\n".to_string(),
-			assemble_response(&units_vec)
+			"0.00171 km is 0.001062544738725841 miles\n140 lbs is 63.50 kg\n".to_string(),
+			assemble_response(&units_vec, &ConversionOptions::default())
 		);
 	}
 
 	#[test]
 	fn assemble_response_degrees() {
 		let msg = "it's -30 c where I live rn";
-		let units_vec = parse_input(msg).unwrap();
+		let units_vec = parse_input(msg, &ConversionOptions::default()).unwrap();
 		assert_eq!(
 			"-30 ℃ is -22.00 ℉\n".to_string(),
-			assemble_response(&units_vec)
+			assemble_response(&units_vec, &ConversionOptions::default())
+		);
+	}
+
+	#[test]
+	fn parsing_msg_ambiguous_volume() {
+		let msg = "the recipe needs 2 gallons of water";
+		assert_eq!(
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![
+				measurement(2.0, "us gallons"),
+				measurement(2.0, "imperial gallons")
+			])
+		);
+	}
+
+	#[test]
+	fn parsing_msg_multi_word_units() {
+		let msg = "pour 8 fl oz into 5 UK gallons of wort";
+		assert_eq!(
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![
+				measurement(8.0, "us fl oz"),
+				measurement(8.0, "imp fl oz"),
+				measurement(5.0, "uk gallons")
+			])
+		);
+	}
+
+	#[test]
+	fn assemble_response_volume() {
+		let msg = "brewing 2 gallons, 3 cups of sugar and 1.5 liters of juice";
+		let units_vec = parse_input(msg, &ConversionOptions::default()).unwrap();
+		assert_eq!(
+			"2 US gal is 7.57 L\n2 imp gal is 9.09 L\n3 cups sugar is 599.75 grams\n1.5 L is 0.3962580785372226 US gal\n"
+				.to_string(),
+			assemble_response(&units_vec, &ConversionOptions::default())
+		);
+	}
+
+	#[test]
+	fn tokenizing_compound_units() {
+		assert_eq!(
+			tokenize("doing 100 km/h, then 10 m / s and 3 ft/ s."),
+			vec!["doing", "100", "km/h", "then", "10", "m/s", "and", "3", "ft/s"]
+		);
+	}
+
+	#[test]
+	fn parsing_msg_speed() {
+		let msg = "did 65 mph on the highway, 100 km/h is the limit and the wind was 10 m / s";
+		assert_eq!(
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![
+				measurement(65.0, "mph"),
+				measurement(100.0, "km/h"),
+				measurement(10.0, "m/s")
+			])
+		);
+	}
+
+	#[test]
+	fn assemble_response_speed() {
+		let msg = "did 65 mph and then 20 knots on the boat";
+		let units_vec = parse_input(msg, &ConversionOptions::default()).unwrap();
+		assert_eq!(
+			"65 mph is 104.61 km/h\n20 knots is 37.04 km/h\n".to_string(),
+			assemble_response(&units_vec, &ConversionOptions::default())
+		);
+	}
+
+	#[test]
+	fn parsing_msg_area() {
+		let msg = "the flat is 500 sq. ft., the garden 20m² and the farm 3 square miles or 2.5 hectares";
+		assert_eq!(
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![
+				measurement(500.0, "sq ft"),
+				measurement(20.0, "m²"),
+				measurement(3.0, "square miles"),
+				measurement(2.5, "hectares")
+			])
+		);
+	}
+
+	#[test]
+	fn assemble_response_area() {
+		let msg = "a 1200 sqft house on 2 acres, the park is 4 km²";
+		let units_vec = parse_input(msg, &ConversionOptions::default()).unwrap();
+		assert_eq!(
+			"1200 ft² is 111.48 m²\n2 acres is 0.8093712844800001 ha\n4 km² is 1.54 sq mi\n".to_string(),
+			assemble_response(&units_vec, &ConversionOptions::default())
 		);
 	}
+
+	#[test]
+	fn parsing_msg_pressure() {
+		let msg = "tyres at 32 psi or 2.2 bar, the barometer says 1013 mbar and 29.92 in hg, bp 120mmHg";
+		assert_eq!(
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![
+				measurement(32.0, "psi"),
+				measurement(2.2, "bar"),
+				measurement(1013.0, "hpa"),
+				measurement(29.92, "inhg"),
+				measurement(120.0, "mmhg")
+			])
+		);
+	}
+
+	#[test]
+	fn assemble_response_pressure() {
+		let msg = "32 psi, 250 kpa, 1 atm and 1013 hPa";
+		let units_vec = parse_input(msg, &ConversionOptions::default()).unwrap();
+		assert_eq!(
+			"32 psi is 2.21 bar\n250 kPa is 36.26 psi\n1 atm is 14.70 psi\n1013 hPa is 29.91 inHg\n".to_string(),
+			assemble_response(&units_vec, &ConversionOptions::default())
+		);
+	}
+
+	#[test]
+	fn parsing_msg_energy_and_power() {
+		let msg =
+			"a 250 Cal snack is 250000 cal, 1046 kJ or 1.5 Calories more, the car has 300 horsepower and the heater 1500W";
+		assert_eq!(
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![
+				measurement(250.0, "kcal"),
+				measurement(250000.0, "cal"),
+				measurement(1046.0, "kj"),
+				measurement(1.5, "kcal"),
+				measurement(300.0, "hp"),
+				measurement(1500.0, "watts")
+			])
+		);
+	}
+
+	#[test]
+	fn assemble_response_energy_and_power() {
+		let msg = "ate 500 calories, used 2.5 kWh and 10000 btu, it makes 150 PS or 110 kW";
+		let units_vec = parse_input(msg, &ConversionOptions::default()).unwrap();
+		assert_eq!(
+			"500 kcal is 2092.00 kJ\n2.5 kWh is 8530.35 BTU\n10000 BTU is 2.93 kWh\n150 PS is 110.32 kW\n110 kW is 147.51 hp\n"
+				.to_string(),
+			assemble_response(&units_vec, &ConversionOptions::default())
+		);
+	}
+
+	#[test]
+	fn registry_case_sensitive_aliases() {
+		assert_eq!(
+			registry().lookup_all("Cal").next().unwrap().name,
+			"kilocalorie"
+		);
+		assert_eq!(registry().lookup_all("cal").next().unwrap().name, "calorie");
+		assert_eq!(registry().lookup_all("CAL").next().unwrap().name, "calorie");
+		assert_eq!(
+			registry().lookup_all("Kilometers").next().unwrap().name,
+			"kilometer"
+		);
+		assert!(!registry().is_ambiguous("W"));
+		assert!(registry().is_ambiguous("w"));
+		assert!(registry().lookup_all("ps").next().is_none());
+		assert_eq!(
+			registry().lookup_all("mb").next().unwrap().name,
+			"hectopascal"
+		);
+		assert!(registry().lookup_all("MB").next().is_none());
+		assert!(registry().lookup_all("Mb").next().is_none());
+		assert!(registry().lookup_all("M").next().is_none());
+		assert!(registry::Registry::parse(
+			"length | meter | m | metric | 1 | 0 | meter | =m\nlength | mile | mi | customary | 1609.344 | 0 | meter | m"
+		)
+		.is_err());
+	}
+
+	#[test]
+	fn parsing_msg_case_sensitive_aliases() {
+		assert_eq!(
+			parse_input(
+				"the video got 5M views, weighs 2MB and my phone has 5G",
+				&with_strictness(Strictness::Relaxed)
+			),
+			Err(ConversionError::NothingToConvert)
+		);
+		assert_eq!(
+			parse_input(
+				"it's 1013mb and 5m away, I ran 10 KM",
+				&ConversionOptions::default()
+			),
+			Ok(vec![
+				measurement(1013.0, "hpa"),
+				measurement(5.0, "m"),
+				measurement(10.0, "km")
+			])
+		);
+	}
+
+	#[test]
+	fn registry_prefixed_units() {
+		let registry = registry::Registry::parse(
+			"prefix | kilo | k | 1e3\nprefix | milli | m | 1e-3\n\
+			length | meter | m | si | 1 | 0 | foot | =m?, meter, meters\n\
+			length | kilometer | km | metric | 1000 | 0 | mile | km, kilometers\n\
+			length | foot | ft | customary | 0.3048 | 0 | meter | ft, feet\n\
+			length | mile | mi | customary | 1609.344 | 0 | kilometer | miles",
+		)
+		.unwrap();
+		let millimeter = registry.lookup_all("mm").next().unwrap();
+		assert_eq!(millimeter.name, "millimeter");
+		assert_eq!(millimeter.symbol, "mm");
+		assert_eq!(millimeter.counterpart.as_deref(), Some("foot"));
+		assert!(approx_eq!(f64, millimeter.factor, 0.001, ulps = 2));
+		assert_eq!(registry.lookup_all("Millimeters").next(), Some(millimeter));
+		assert!(registry.lookup_all("MM").next().is_none());
+		assert!(!registry.is_ambiguous("mm"));
+		// explicit definitions win over generated ones but still belong to the family
+		assert_eq!(
+			registry
+				.lookup_all("km")
+				.next()
+				.unwrap()
+				.counterpart
+				.as_deref(),
+			Some("mile")
+		);
+		assert_eq!(registry.family(millimeter).unwrap().len(), 3);
+		assert!(
+			registry::Registry::parse("prefix | kilo | k | 1e3\nlength | meter | m | si | 1 | 0 | meter | meter")
+				.is_err()
+		);
+	}
+
+	#[test]
+	fn parsing_msg_prefixed_units() {
+		// micrometers and milligrams have no customary unit of their size and are only converted on request
+		let msg = "a 5mm screw, a 250 µm layer, 3 megawatts, 40 kN and 500 mg";
+		assert_eq!(
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![
+				measurement(5.0, "mm"),
+				measurement(3.0, "MW"),
+				measurement(40.0, "kN")
+			])
+		);
+		assert_eq!(
+			respond_to_msg("a 3 nm process", &ConversionOptions::default()),
+			Err(ConversionError::NothingToConvert)
+		);
+		// every SI prefix, and the units only converted on request stay out of the time of day
+		assert_eq!(
+			respond_to_msg(
+				"the meeting is at 5 pm, the chip runs at 3 GHz",
+				&ConversionOptions::default()
+			),
+			Err(ConversionError::NothingToConvert)
+		);
+		assert_eq!(measurement(1.0, "pm").unit.name, "picometer");
+		assert_eq!(measurement(1.0, "PJ").unit.name, "petajoule");
+		assert_eq!(measurement(1.0, "hL").unit.name, "hectoliter");
+		assert_eq!(
+			convert_command("3 GHz to MHz", &ConversionOptions::default()),
+			Ok("3 GHz is 3000.00 MHz".to_string())
+		);
+		assert_eq!(measurement(250.0, "μm").unit, measurement(250.0, "µm").unit);
+		assert_eq!(
+			parse_input("5 MM", &with_strictness(Strictness::Relaxed)),
+			Err(ConversionError::NothingToConvert)
+		);
+	}
+
+	#[test]
+	fn assemble_response_friendly_prefixes() {
+		let msg =
+			"a 20000 feet climb, a 0.01 inch gap, 1000 gallons, 180 cm and 2 inches of snow, 5 mm, 40 cups of flour";
+		let units_vec = parse_input(msg, &ConversionOptions::default()).unwrap();
+		// counterparts named in units.def are kept as they are
+		assert_eq!(
+			"20000 ft is 6096.00 m\n0.01 in is 0.0254 cm\n1000 US gal is 3785.41 L\n1000 imp gal is 4546.09 L\n\
+			180 cm is 5 ft 10.9 in\n2 in is 5.08 cm\n5 mm is 0.1968503937007874 in\n40 cups flour is 5.00 kg\n"
+				.to_string(),
+			assemble_response(&units_vec, &ConversionOptions::default())
+		);
+	}
+
+	#[test]
+	fn parsing_msg_kelvin_and_rankine() {
+		let msg = "liquid nitrogen boils at 77 kelvin, which is 138.6 °R";
+		assert_eq!(
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![measurement(77.0, "kelvin"), measurement(138.6, "°r")])
+		);
+		assert_eq!(
+			parse_input("I ran a 5K today", &with_strictness(Strictness::Relaxed)),
+			Err(ConversionError::NothingToConvert)
+		);
+	}
+
+	#[test]
+	fn assemble_response_kelvin_and_rankine() {
+		let msg = "it's 300 kelvin, 0 °K or 540 °R";
+		let units_vec = parse_input(msg, &ConversionOptions::default()).unwrap();
+		assert_eq!(
+			"300 K is 26.85 ℃\n0 K is -273.15 ℃\n540 °R is 300.00 K\n".to_string(),
+			assemble_response(&units_vec, &ConversionOptions::default())
+		);
+	}
+
+	#[test]
+	fn convert_command_kelvin() {
+		assert_eq!(
+			convert_command("300 K to c", &ConversionOptions::default()),
+			Ok("300 K is 26.85 ℃".to_string())
+		);
+	}
+
+	#[test]
+	fn parsing_msg_temperature_differences() {
+		let msg = "it was 10 c, then the temperature rose by 10 c and it's 5°F warmer than yesterday, a Δ°C of 3";
+		assert_eq!(
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![
+				measurement(10.0, "℃"),
+				measurement(10.0, "Δ℃"),
+				measurement(5.0, "Δ℉")
+			])
+		);
+		assert_eq!(
+			parse_input(
+				"a change of 4 degrees f and 2 Δk",
+				&ConversionOptions::default()
+			),
+			Ok(vec![measurement(4.0, "Δ℉"), measurement(2.0, "ΔK")])
+		);
+	}
+
+	#[test]
+	fn assemble_response_temperature_differences() {
+		let msg = "it was 10 c, then it rose by 10 c and tomorrow will be 9 ℉ colder";
+		let units_vec = parse_input(msg, &ConversionOptions::default()).unwrap();
+		assert_eq!(
+			"10 ℃ is 50.00 ℉\n10 Δ℃ is 18.00 Δ℉\n9 Δ℉ is 5.00 Δ℃\n".to_string(),
+			assemble_response(&units_vec, &ConversionOptions::default())
+		);
+	}
+
+	#[test]
+	fn tokenizing_attached_units() {
+		assert_eq!(
+			tokenize("i'm 171cm, -3°c outside and 100km/h. 2nd 3e8"),
+			vec!["i'm", "171", "cm", "-3", "°c", "outside", "and", "100", "km/h", "2", "nd", "3e8"]
+		);
+	}
+
+	#[test]
+	fn parsing_msg_attached_units() {
+		let msg = "I'm 171cm and 5kg heavier than last year";
+		assert_eq!(
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![measurement(171.0, "cm"), measurement(5.0, "kg")])
+		);
+	}
+
+	#[test]
+	fn parsing_msg_degree_sign() {
+		let msg = "it's 30°C today, -3°C tonight and 72°F in New York, 20 °C tomorrow";
+		assert_eq!(
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![
+				measurement(30.0, "℃"),
+				measurement(-3.0, "℃"),
+				measurement(72.0, "℉"),
+				measurement(20.0, "℃")
+			])
+		);
+	}
+
+	#[test]
+	fn assemble_response_attached_units() {
+		let msg = "it's -3°C and I ran 10km";
+		let units_vec = parse_input(msg, &ConversionOptions::default()).unwrap();
+		assert_eq!(
+			"-3 ℃ is 26.60 ℉\n10 km is 6.21 miles\n".to_string(),
+			assemble_response(&units_vec, &ConversionOptions::default())
+		);
+	}
+
+	fn compound(value: f64, alias: &str) -> Measurement {
+		Measurement {
+			compound: true,
+			..measurement(value, alias)
+		}
+	}
+
+	#[test]
+	fn tokenizing_feet_and_inches() {
+		assert_eq!(
+			tokenize("i'm 5'11\", you're 6'."),
+			vec!["i'm", "5", "'", "11", "\"", "you're", "6", "'"]
+		);
+		assert_eq!(
+			tokenize("5'11 or 5′11″"),
+			vec!["5", "'", "11", "\"", "or", "5", "′", "11", "″"]
+		);
+	}
+
+	#[test]
+	fn parsing_msg_compound_quantities() {
+		let msg = "I'm 5'11\" and my brother is 6 ft 2 in, the baby weighed 7 lb 4 oz";
+		let units_vec = parse_input(msg, &ConversionOptions::default()).unwrap();
+		let expected = [
+			compound(71.0, "inches"),
+			compound(74.0, "inches"),
+			compound(116.0, "oz"),
+		];
+		assert_eq!(units_vec.len(), expected.len());
+		for (parsed, expected) in units_vec.iter().zip(&expected) {
+			assert_approx_eq(parsed, expected);
+		}
+	}
+
+	#[test]
+	fn parsing_msg_compound_part_alias_alone() {
+		let msg = "6 ft tall, 2 in a row";
+		assert_eq!(
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![measurement(6.0, "ft")])
+		);
+	}
+
+	#[test]
+	fn assemble_response_compound_quantities() {
+		let msg = "I'm 5'11 and 180 cm, 500 grams and 1 lb 4 oz";
+		let units_vec = parse_input(msg, &ConversionOptions::default()).unwrap();
+		assert_eq!(
+			"5 ft 11 in is 180.34 cm\n180 cm is 5 ft 10.9 in\n500 grams is 1 lbs 1.6 oz\n1 lbs 4 oz is 566.99 grams\n"
+				.to_string(),
+			assemble_response(&units_vec, &ConversionOptions::default())
+		);
+	}
+
+	#[test]
+	fn assemble_response_compound_unit_alone() {
+		let units_vec = parse_input("I weigh 150 lbs", &ConversionOptions::default()).unwrap();
+		assert_eq!(
+			"150 lbs is 68.04 kg\n".to_string(),
+			assemble_response(&units_vec, &ConversionOptions::default())
+		);
+	}
+
+	#[test]
+	fn convert_command_multiple_targets() {
+		assert_eq!(
+			convert_command("5 km to miles, ft and yards", &ConversionOptions::default()),
+			Ok("5 km is 3.11 miles, 16404.20 ft, 5468.07 yd".to_string())
+		);
+		assert_eq!(
+			convert_command("5 ft 11 in to cm", &ConversionOptions::default()),
+			Ok("5 ft 11 in is 180.34 cm".to_string())
+		);
+		assert_eq!(
+			convert_command("20 liters to gallons", &ConversionOptions::default()),
+			Ok("20 L is 5.28 US gal, 4.40 imp gal".to_string())
+		);
+	}
+
+	#[test]
+	fn convert_command_part_aliases() {
+		assert_eq!(
+			convert_command("5 in to cm", &ConversionOptions::default()),
+			Ok("5 in is 12.70 cm".to_string())
+		);
+	}
+
+	#[test]
+	fn convert_command_derived_units() {
+		assert_eq!(
+			convert_command("1000 ft/min to km/h", &ConversionOptions::default()),
+			Ok("1000 ft/min is 18.29 km/h".to_string())
+		);
+		assert_eq!(
+			convert_command("60 mph to km/min", &ConversionOptions::default()),
+			Ok("60 mph is 1.61 km/min".to_string())
+		);
+		assert_eq!(
+			convert_command("3 kWh/day to W", &ConversionOptions::default()),
+			Ok("3 kWh/day is 125.00 W".to_string())
+		);
+		assert_eq!(
+			convert_command("10 ft/min to kg", &ConversionOptions::default()),
+			Err(ConversionError::MismatchedDimensions(
+				"speed".to_string(),
+				"mass".to_string()
+			))
+		);
+		assert_eq!(
+			convert_command("20 c to ft/min", &ConversionOptions::default()),
+			Err(ConversionError::AbsoluteTemperature(
+				registry().lookup_all("c").next().unwrap()
+			))
+		);
+	}
+
+	#[test]
+	fn assemble_response_ingredients() {
+		let msg = "mix 2 cups flour with 2 sticks of butter and 1 tbsp of honey";
+		let units_vec = parse_input(msg, &ConversionOptions::default()).unwrap();
+		assert_eq!(
+			"2 cups flour is 249.84 grams\n2 sticks is 226.80 grams\n1 tbsp honey is 21.00 grams\n".to_string(),
+			assemble_response(&units_vec, &ConversionOptions::default())
+		);
+		let imperial = ConversionOptions {
+			system: PreferredSystem::Imperial,
+			..ConversionOptions::default()
+		};
+		let units_vec = parse_input("2 cups of flour", &imperial).unwrap();
+		assert_eq!(
+			"2 cups flour is 8.81 oz\n".to_string(),
+			assemble_response(&units_vec, &imperial)
+		);
+		// liquids stay volumes unless they're measured by the cup or the spoon
+		let units_vec = parse_input("I drank 2 liters of water and 1 cup of milk", &imperial).unwrap();
+		assert_eq!(
+			"2 L is 1.76 imp qt\n1 cups milk is 8.64 oz\n".to_string(),
+			assemble_response(&units_vec, &imperial)
+		);
+	}
+
+	#[test]
+	fn convert_command_ingredients() {
+		assert_eq!(
+			convert_command("250 g sugar to cups", &ConversionOptions::default()),
+			Ok("250 grams sugar is 1.25 cups".to_string())
+		);
+		assert_eq!(
+			convert_command(
+				"2 cups of flour to grams, oz",
+				&ConversionOptions::default()
+			),
+			Ok("2 cups flour is 249.84 grams, 8.81 oz".to_string())
+		);
+		assert_eq!(
+			convert_command("1 tsp to ml", &ConversionOptions::default()),
+			Ok("1 tsp is 4.93 mL".to_string())
+		);
+		assert_eq!(
+			convert_command("250 g to cups", &ConversionOptions::default()),
+			Err(ConversionError::IncompatibleUnits(
+				registry().lookup_all("g").next().unwrap(),
+				registry().lookup_all("cups").next().unwrap()
+			))
+		);
+	}
+
+	#[test]
+	fn assemble_response_fuel_economy() {
+		let msg = "my car does 6.5 l/100km, the old one did 30-35 mpg";
+		let units_vec = parse_input(msg, &ConversionOptions::default()).unwrap();
+		assert_eq!(
+			"6.5 L/100km is 36.19 US mpg\n30–35 US mpg is 6.72–7.84 L/100km\n30–35 imp mpg is 8.07–9.42 L/100km\n"
+				.to_string(),
+			assemble_response(&units_vec, &ConversionOptions::default())
+		);
+		let imperial = ConversionOptions {
+			system: PreferredSystem::Imperial,
+			..ConversionOptions::default()
+		};
+		let units_vec = parse_input("it uses 5 L/100km", &imperial).unwrap();
+		assert_eq!(
+			"5 L/100km is 56.50 imp mpg\n".to_string(),
+			assemble_response(&units_vec, &imperial)
+		);
+	}
+
+	#[test]
+	fn convert_command_fuel_economy() {
+		assert_eq!(
+			convert_command("40 mpg to l/100km, km/l", &ConversionOptions::default()),
+			Ok("40 US mpg is 5.88 L/100km, 17.01 km/L".to_string())
+		);
+		assert_eq!(
+			convert_command(
+				"8 L/100 km to us mpg, uk mpg",
+				&ConversionOptions::default()
+			),
+			Ok("8 L/100km is 29.40 US mpg, 35.31 imp mpg".to_string())
+		);
+		assert_eq!(
+			convert_command("0 mpg to l/100km", &ConversionOptions::default()),
+			Err(ConversionError::DivisionByZero)
+		);
+		assert_eq!(
+			convert_command("1e308 miles to nm", &ConversionOptions::default()),
+			Err(ConversionError::Overflow)
+		);
+		assert_eq!(
+			convert_command("1e308 mph to nm/min", &ConversionOptions::default()),
+			Err(ConversionError::Overflow)
+		);
+		assert_eq!(
+			respond_to_msg("it does 0 mpg", &ConversionOptions::default()),
+			Err(ConversionError::NothingToConvert)
+		);
+	}
+
+	#[test]
+	fn convert_command_errors() {
+		assert_eq!(
+			convert_command("5 kg to km", &ConversionOptions::default()),
+			Err(ConversionError::IncompatibleUnits(
+				registry().lookup_all("kg").next().unwrap(),
+				registry().lookup_all("km").next().unwrap()
+			))
+		);
+		assert_eq!(
+			convert_command("5 kg to km", &ConversionOptions::default())
+				.unwrap_err()
+				.to_string(),
+			"kg is mass, km is length"
+		);
+		assert_eq!(
+			convert_command("5 parsecs to km", &ConversionOptions::default()),
+			Err(ConversionError::UnknownUnit("parsecs".to_string()))
+		);
+		assert_eq!(
+			convert_command("fiver km to miles", &ConversionOptions::default()),
+			Err(ConversionError::InvalidNumber("fiver".to_string()))
+		);
+		assert_eq!(
+			convert_command("5 km 3 to miles", &ConversionOptions::default()),
+			Err(ConversionError::UnexpectedInput("3".to_string()))
+		);
+		assert_eq!(
+			convert_command("5 km", &ConversionOptions::default()),
+			Err(ConversionError::Usage(CONVERT_USAGE))
+		);
+		assert_eq!(
+			convert_command("5 km to", &ConversionOptions::default()),
+			Err(ConversionError::Usage(CONVERT_USAGE))
+		);
+	}
+
+	#[test]
+	fn parsing_msg_starting_with_unit() {
+		assert_eq!(
+			parse_input("cm", &ConversionOptions::default()),
+			Err(ConversionError::NothingToConvert)
+		);
+		assert_eq!(
+			parse_input("m8", &ConversionOptions::default()),
+			Err(ConversionError::NothingToConvert)
+		);
+		assert_eq!(
+			parse_input("' 5 feet", &ConversionOptions::default()),
+			Ok(vec![measurement(5.0, "ft")])
+		);
+	}
+
+	#[test]
+	fn parsing_msg_non_finite_numbers() {
+		assert_eq!(
+			parse_input(
+				"nan km and inf kg, 1e999 miles",
+				&ConversionOptions::default()
+			),
+			Err(ConversionError::NothingToConvert)
+		);
+		assert_eq!(
+			convert_command("nan km to miles", &ConversionOptions::default()),
+			Err(ConversionError::InvalidNumber("nan".to_string()))
+		);
+	}
+
+	#[test]
+	fn parsing_msg_ignores_units_without_counterpart() {
+		assert_eq!(
+			parse_input("it took 25 min to run 5 km", &ConversionOptions::default()),
+			Ok(vec![measurement(5.0, "km")])
+		);
+	}
+
+	#[test]
+	fn tokenizing_number_formats() {
+		assert_eq!(
+			tokenize("1,500km 10m/s ½mile 1/2cup 3e8m 2.5k"),
+			vec!["1,500", "km", "10", "m/s", "½", "mile", "1/2", "cup", "3e8", "m", "2.5k"]
+		);
+	}
+
+	#[test]
+	fn parsing_msg_number_formats() {
+		let msg = "drove 1,500 km and 1.5k miles, then ½ mile, 1 1/2 miles and 2¼ miles more, light does 3e8 meters";
+		assert_eq!(
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![
+				measurement(1500.0, "km"),
+				measurement(1500.0, "miles"),
+				measurement(0.5, "miles"),
+				measurement(1.5, "miles"),
+				measurement(2.25, "miles"),
+				measurement(3e8, "meters")
+			])
+		);
+	}
+
+	#[test]
+	fn parsing_msg_decimal_mark() {
+		let msg = "it weighs 1,500 kg, or 2,5 kg";
+		let comma = ConversionOptions {
+			decimal_mark: DecimalMark::Comma,
+			..ConversionOptions::default()
+		};
+		assert_eq!(
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![measurement(1500.0, "kg"), measurement(2.5, "kg")])
+		);
+		assert_eq!(
+			parse_input(msg, &comma),
+			Ok(vec![measurement(1.5, "kg"), measurement(2.5, "kg")])
+		);
+	}
+
+	#[test]
+	fn convert_command_number_formats() {
+		assert_eq!(
+			convert_command("1 1/2 miles to km", &ConversionOptions::default()),
+			Ok(String::from("1.5 miles is 2.41 km"))
+		);
+		assert_eq!(
+			convert_command("1,5 km to m", &ConversionOptions::default()),
+			Ok(String::from("1.5 km is 1500.00 m"))
+		);
+	}
+
+	#[test]
+	fn parsing_msg_spelled_numbers() {
+		let cases = [
+			("I walked five miles today", measurement(5.0, "miles")),
+			("my dog weighs a hundred pounds", measurement(100.0, "lbs")),
+			(
+				"grab half a kilo of rice",
+				with_ingredient(measurement(0.5, "kg"), "rice"),
+			),
+			("it weighed a couple of kilos", measurement(2.0, "kg")),
+			(
+				"the shelf is two and a half feet wide",
+				measurement(2.5, "ft"),
+			),
+			("only Twenty-five km to go", measurement(25.0, "km")),
+			(
+				"about one hundred and fifty meters",
+				measurement(150.0, "meters"),
+			),
+			(
+				"we walked three quarters of a mile",
+				measurement(0.75, "miles"),
+			),
+			("half a dozen km away", measurement(6.0, "km")),
+		];
+		for (msg, expected) in cases {
+			assert_eq!(
+				parse_input(msg, &ConversionOptions::default()),
+				Ok(vec![expected]),
+				"{}",
+				msg
+			);
+		}
+	}
+
+	#[test]
+	fn parsing_msg_spelled_numbers_need_a_number() {
+		let options = ConversionOptions::default();
+		assert_eq!(
+			parse_input("he hurt a foot", &options),
+			Err(ConversionError::NothingToConvert)
+		);
+		for msg in ["one foot in the door", "with one foot in the grave"] {
+			assert_eq!(
+				parse_input(msg, &options),
+				Err(ConversionError::NothingToConvert)
+			);
+		}
+		assert_eq!(
+			parse_input("one foot in the door", &with_strictness(Strictness::Strict)),
+			Err(ConversionError::NothingToConvert)
+		);
+		assert_eq!(
+			parse_input(
+				"Twenty-five km to go",
+				&with_strictness(Strictness::Relaxed)
+			),
+			Ok(vec![measurement(25.0, "km")])
+		);
+		// "and" between two numbers doesn't add them up
+		assert_eq!(
+			parse_input("it is between five and ten miles away", &options),
+			Ok(vec![measurement(10.0, "miles")])
+		);
+		assert_eq!(
+			parse_input("the trails are two and five miles long", &options),
+			Ok(vec![measurement(5.0, "miles")])
+		);
+		assert_eq!(
+			parse_input("he is five feet two inches tall", &options),
+			Ok(vec![Measurement {
+				compound: true,
+				..measurement(62.0, "inches")
+			}])
+		);
+	}
+
+	#[test]
+	fn convert_command_spelled_numbers() {
+		assert_eq!(
+			convert_command("two and a half miles to km", &ConversionOptions::default()),
+			Ok(String::from("2.5 miles is 4.02 km"))
+		);
+	}
+
+	fn range(low: f64, value: f64, alias: &str) -> Measurement {
+		Measurement {
+			low: Some(low),
+			..measurement(value, alias)
+		}
+	}
+
+	#[test]
+	fn tokenizing_ranges_and_approximations() {
+		assert_eq!(
+			tokenize("20-25°C, 5 – 10km and ~3 miles"),
+			vec!["20", "-", "25", "°C", "5", "–", "10", "km", "and", "~", "3", "miles"]
+		);
+	}
+
+	#[test]
+	fn parsing_msg_ranges() {
+		let msg = "it'll be 20-25 c tomorrow, so run 5 to 10 km or five to ten miles. -5 – -10 °C at night";
+		assert_eq!(
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![
+				range(20.0, 25.0, "℃"),
+				range(5.0, 10.0, "km"),
+				range(5.0, 10.0, "miles"),
+				range(-10.0, -5.0, "℃")
+			])
+		);
+	}
+
+	#[test]
+	fn parsing_msg_approximate_quantities() {
+		let msg = "it's ~3 miles, approx. 20-25 kg or ≈ 5 ft";
+		assert_eq!(
+			parse_input(msg, &ConversionOptions::default()),
+			Ok(vec![
+				Measurement {
+					approximate: true,
+					..measurement(3.0, "miles")
+				},
+				Measurement {
+					approximate: true,
+					..range(20.0, 25.0, "kg")
+				},
+				Measurement {
+					approximate: true,
+					..measurement(5.0, "feet")
+				}
+			])
+		);
+	}
+
+	#[test]
+	fn assemble_response_ranges() {
+		let options = ConversionOptions::default();
+		assert_eq!(
+			respond_to_msg("20-25 c and ~3 miles", &options),
+			Ok(String::from(
+				"20–25 ℃ is 68.00–77.00 ℉\n~3 miles is ~4.83 km\n"
+			))
+		);
+		assert_eq!(
+			respond_to_msg("they're 170 to 180 cm tall", &options),
+			Ok(String::from("170–180 cm is 5 ft 6.9 in–5 ft 10.9 in\n"))
+		);
+		assert_eq!(
+			respond_to_msg("-5 – -10 °C at night", &options),
+			Ok(String::from("-10–-5 ℃ is 14.00–23.00 ℉\n"))
+		);
+	}
+
+	// words that are likely to reach the deeper parts of the parser
+	fn unit_like_words() -> impl Strategy<Value = String> {
+		let aliases: Vec<String> = registry().aliases().map(String::from).collect();
+		prop_oneof![
+			any::<f64>().prop_map(|value| value.to_string()),
+			any::<i16>().prop_map(|value| value.to_string()),
+			prop::sample::select(aliases),
+			prop::sample::select(vec![
+				"to", "and", "'", "\"", "/", "°", ".", ",", "-", "½", "1/2", "1,500", "2k", "five", "a", "half",
+				"hundred", "+", "*", "^", "(", ")", "in", ""
+			])
+			.prop_map(String::from),
+			"\\PC{0,4}",
+		]
+	}
+
+	fn unit_like_msg() -> impl Strategy<Value = String> {
+		prop::collection::vec(
+			(
+				unit_like_words(),
+				prop::sample::select(vec![" ", "", "/", " / ", "'", "\n"]),
+			),
+			0..16,
+		)
+		.prop_map(|words| {
+			words
+				.into_iter()
+				.map(|(word, separator)| word + separator)
+				.collect()
+		})
+	}
+
+	fn with_strictness(strictness: Strictness) -> ConversionOptions {
+		ConversionOptions {
+			strictness,
+			..ConversionOptions::default()
+		}
+	}
+
+	#[test]
+	fn parsing_msg_ambiguous_aliases() {
+		let normal = with_strictness(Strictness::Normal);
+		assert_eq!(
+			parse_input("we were top 2 c tier", &normal),
+			Err(ConversionError::NothingToConvert)
+		);
+		assert_eq!(
+			parse_input("got 2 c and a b", &normal),
+			Err(ConversionError::NothingToConvert)
+		);
+		assert_eq!(
+			parse_input("it's 30 c outside", &normal),
+			Ok(vec![measurement(30.0, "c")])
+		);
+		assert_eq!(
+			parse_input("wow 30c", &normal),
+			Err(ConversionError::NothingToConvert)
+		);
+		assert_eq!(
+			parse_input("I'm 6 ft", &normal),
+			Ok(vec![measurement(6.0, "ft")])
+		);
+		assert_eq!(
+			parse_input("about 3 km", &normal),
+			Ok(vec![measurement(3.0, "km")])
+		);
+	}
+
+	#[test]
+	fn parsing_msg_strictness_levels() {
+		let msg = "got 2 c and a b";
+		assert_eq!(
+			parse_input(msg, &with_strictness(Strictness::Relaxed)),
+			Ok(vec![measurement(2.0, "c")])
+		);
+		assert_eq!(
+			parse_input("grade 5 f", &with_strictness(Strictness::Relaxed)),
+			Err(ConversionError::NothingToConvert)
+		);
+
+		let strict = with_strictness(Strictness::Strict);
+		assert_eq!(
+			parse_input("it's 30 c outside", &strict),
+			Err(ConversionError::NothingToConvert)
+		);
+		assert_eq!(
+			parse_input("it's 30°c outside", &strict),
+			Ok(vec![measurement(30.0, "c")])
+		);
+		assert_eq!(
+			parse_input("it's 30c outside", &strict),
+			Ok(vec![measurement(30.0, "c")])
+		);
+		assert_eq!(
+			parse_input("the video got 5m views", &strict),
+			Err(ConversionError::NothingToConvert)
+		);
+		assert_eq!(
+			parse_input("I paid 99c", &strict),
+			Err(ConversionError::NothingToConvert)
+		);
+		assert_eq!(
+			parse_input(
+				"the video got 5m views",
+				&with_strictness(Strictness::Normal)
+			),
+			Err(ConversionError::NothingToConvert)
+		);
+		let units_vec = parse_input("I'm 6 ft 2 in", &strict).unwrap();
+		assert_approx_eq(&units_vec[0], &compound(74.0, "inches"));
+	}
+
+	#[test]
+	fn strictness_from_str() {
+		assert_eq!("Strict".parse::<Strictness>(), Ok(Strictness::Strict));
+		assert_eq!("relaxed".parse::<Strictness>(), Ok(Strictness::Relaxed));
+		assert!("loose".parse::<Strictness>().is_err());
+		assert_eq!(Strictness::Normal.to_string(), "normal");
+	}
+
+	fn with_system(system: PreferredSystem) -> ConversionOptions {
+		ConversionOptions {
+			system,
+			..ConversionOptions::default()
+		}
+	}
+
+	#[test]
+	fn respond_to_msg_preferred_system() {
+		let metric = with_system(PreferredSystem::Metric);
+		assert_eq!(
+			respond_to_msg("I ran 5 km, then 3 miles", &metric),
+			Ok("3 miles is 4.83 km\n".to_string())
+		);
+		assert_eq!(
+			respond_to_msg("I ran 5 km", &metric),
+			Err(ConversionError::NothingToConvert)
+		);
+		assert_eq!(
+			respond_to_msg("it's 5 ft 11 in tall", &metric),
+			Ok("5 ft 11 in is 180.34 cm\n".to_string())
+		);
+
+		let us = with_system(PreferredSystem::Us);
+		assert_eq!(
+			respond_to_msg("we need 5 gallons and 20 liters", &us),
+			Ok("20 L is 5.28 US gal\n".to_string())
+		);
+		assert_eq!(
+			respond_to_msg("we need 5 imperial gallons", &us),
+			Ok("5 imp gal is 6.00 US gal\n".to_string())
+		);
+		assert_eq!(
+			respond_to_msg("it was 20 ℃", &us),
+			Ok("20 ℃ is 68.00 ℉\n".to_string())
+		);
+		assert_eq!(
+			respond_to_msg("a 500 kcal meal", &us),
+			Err(ConversionError::NothingToConvert)
+		);
+		assert_eq!(
+			respond_to_msg("a 2000 kJ meal", &us),
+			Ok("2000 kJ is 478.01 kcal\n".to_string())
+		);
+
+		let imperial = with_system(PreferredSystem::Imperial);
+		assert_eq!(
+			respond_to_msg("a 2 pints or 5 us gallons", &imperial),
+			Ok("5 US gal is 4.16 imp gal\n".to_string())
+		);
+		// there is no imperial temperature scale, the counterpart is used instead
+		assert_eq!(
+			respond_to_msg("it was 68 ℉", &imperial),
+			Ok("68 ℉ is 20.00 ℃\n".to_string())
+		);
+	}
+
+	#[test]
+	fn respond_to_msg_ignores_markdown() {
+		let options = ConversionOptions::default();
+		assert_eq!(
+			respond_to_msg("`let width = 5 m;` <:kg:123> https://x.com/12-km", &options),
+			Err(ConversionError::NothingToConvert)
+		);
+		assert_eq!(
+			respond_to_msg("> it was 10 km\nmore like 20 km", &options),
+			Ok("20 km is 12.43 miles\n".to_string())
+		);
+	}
+
+	#[test]
+	fn command_messages_are_not_converted() {
+		for msg in [
+			"!convert 5 km to miles",
+			"!calc 5 ft + 3 in in cm",
+			"  !recipe scale x2\n2 cups flour",
+		] {
+			assert!(is_command(msg));
+		}
+		assert!(!is_command("ran 5 km! then 2 miles"));
+	}
+
+	proptest! {
+		#[test]
+		fn respond_to_msg_never_panics(msg in "\\PC*") {
+			let _ = respond_to_msg(&msg, &ConversionOptions::default());
+			let _ = convert_command(&msg, &ConversionOptions::default());
+			let _ = calc_command(&msg, &ConversionOptions::default());
+		}
+
+		#[test]
+		fn respond_to_msg_never_panics_on_unit_like_msg(msg in unit_like_msg()) {
+			let _ = respond_to_msg(&msg, &ConversionOptions::default());
+			let _ = convert_command(&msg, &ConversionOptions::default());
+			let _ = calc_command(&msg, &ConversionOptions::default());
+		}
+	}
 }
@@ -0,0 +1,251 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use super::error::ConversionError;
+
+// which separator a guild uses for decimals, decides whether "1,500" is 1500 or 1.5
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DecimalMark {
+	#[default]
+	Point,
+	Comma,
+}
+
+impl DecimalMark {
+	fn separator(self) -> char {
+		match self {
+			DecimalMark::Point => '.',
+			DecimalMark::Comma => ',',
+		}
+	}
+}
+
+impl FromStr for DecimalMark {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"point" | "." => Ok(DecimalMark::Point),
+			"comma" | "," => Ok(DecimalMark::Comma),
+			_ => Err(format!("'{}' isn't point or comma", s)),
+		}
+	}
+}
+
+impl fmt::Display for DecimalMark {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let name = match self {
+			DecimalMark::Point => "point",
+			DecimalMark::Comma => "comma",
+		};
+		write!(f, "{}", name)
+	}
+}
+
+pub const VULGAR_FRACTIONS: &[(char, f64)] = &[
+	('½', 1.0 / 2.0),
+	('⅓', 1.0 / 3.0),
+	('⅔', 2.0 / 3.0),
+	('¼', 1.0 / 4.0),
+	('¾', 3.0 / 4.0),
+	('⅕', 1.0 / 5.0),
+	('⅖', 2.0 / 5.0),
+	('⅗', 3.0 / 5.0),
+	('⅘', 4.0 / 5.0),
+	('⅙', 1.0 / 6.0),
+	('⅚', 5.0 / 6.0),
+	('⅛', 1.0 / 8.0),
+	('⅜', 3.0 / 8.0),
+	('⅝', 5.0 / 8.0),
+	('⅞', 7.0 / 8.0),
+];
+
+pub fn is_vulgar_fraction(c: char) -> bool {
+	VULGAR_FRACTIONS.iter().any(|&(fraction, _)| fraction == c)
+}
+
+// "1/2" and "½" are fractions, a whole number in front of them adds up ("1 1/2 cups")
+pub fn is_fraction(word: &str) -> bool {
+	word.contains('/') || word.starts_with(is_vulgar_fraction)
+}
+
+// reads numbers the way people write them: "1,500", "2,5", "1/2", "1½", "3e8" and "1.5k",
+// only finite numbers count, "nan" and "inf" parse as f64 but aren't measurements
+pub fn parse_number(word: &str, decimal_mark: DecimalMark) -> Result<f64, ConversionError> {
+	let invalid = || ConversionError::InvalidNumber(word.to_string());
+	let value = if let Some(thousands) = word.strip_suffix(['k', 'K']) {
+		parse_plain(thousands, decimal_mark).map(|value| value * 1000.0)
+	} else if let Some((numerator, denominator)) = word.split_once('/') {
+		match (numerator.parse::<i64>(), denominator.parse::<u64>()) {
+			(Ok(numerator), Ok(denominator)) if denominator != 0 => Some(numerator as f64 / denominator as f64),
+			_ => None,
+		}
+	} else if let Some(fraction) = word.chars().last().filter(|&c| is_vulgar_fraction(c)) {
+		let whole = &word[..word.len() - fraction.len_utf8()];
+		let fraction = VULGAR_FRACTIONS
+			.iter()
+			.find(|&&(c, _)| c == fraction)
+			.map(|&(_, value)| value)
+			.unwrap_or_default();
+		match whole {
+			"" => Some(fraction),
+			"-" => Some(-fraction),
+			whole => whole.parse::<i64>().ok().map(|whole| {
+				if whole < 0 || word.starts_with('-') {
+					whole as f64 - fraction
+				} else {
+					whole as f64 + fraction
+				}
+			}),
+		}
+	} else {
+		parse_plain(word, decimal_mark)
+	};
+	match value {
+		Some(value) if value.is_finite() => Ok(value),
+		_ => Err(invalid()),
+	}
+}
+
+// a decimal number with optional thousands separators, when only one separator is used once
+// and followed by three digits ("1,500" or "1.500") the guild's decimal mark decides
+fn parse_plain(word: &str, decimal_mark: DecimalMark) -> Option<f64> {
+	// where commas are the decimal mark "1.500" groups thousands
+	let grouped = decimal_mark == DecimalMark::Comma
+		&& word.contains('.')
+		&& word
+			.split('.')
+			.skip(1)
+			.all(|group| group.len() == 3 && group.chars().all(|c| c.is_ascii_digit()));
+	if !word.contains(',') && !grouped {
+		if let Ok(value) = word.parse::<f64>() {
+			return Some(value);
+		}
+	}
+	let last = word.rfind([',', '.'])?;
+	let separator = word[last..].chars().next()?;
+	let other = if separator == ',' { '.' } else { ',' };
+	let decimal = if word.contains(other) {
+		// "1,500.5" and "1.500,5", the last separator is the decimal one
+		Some(separator)
+	} else if word.matches(separator).count() > 1 {
+		// "1,500,000", only thousands repeat
+		None
+	} else if word.len() - last - 1 == 3 && separator != decimal_mark.separator() {
+		None
+	} else {
+		Some(separator)
+	};
+	let (whole, fraction) = match decimal {
+		Some(decimal) => {
+			let (whole, fraction) = word.split_at(last);
+			if whole.contains(decimal) {
+				return None;
+			}
+			(whole, &fraction[1..])
+		}
+		None => (word, ""),
+	};
+	let thousands = match decimal {
+		Some(decimal) if decimal == separator => other,
+		_ => separator,
+	};
+	if !fraction.chars().all(|c| c.is_ascii_digit()) {
+		return None;
+	}
+	let digits = remove_thousands(whole, thousands)?;
+	format!("{}.{}", digits, fraction).parse().ok()
+}
+
+// "1,500,000" into "1500000", groups after the first have to be three digits long
+fn remove_thousands(whole: &str, separator: char) -> Option<String> {
+	let (sign, digits) = match whole.strip_prefix(['-', '+']) {
+		Some(digits) => (&whole[..1], digits),
+		None => ("", whole),
+	};
+	let mut groups = digits.split(separator);
+	let first = groups.next()?;
+	if first.is_empty() || first.len() > 3 && digits.contains(separator) {
+		return None;
+	}
+	let mut number = format!("{}{}", sign, first);
+	for group in groups {
+		if group.len() != 3 {
+			return None;
+		}
+		number.push_str(group);
+	}
+	if number[sign.len()..].chars().all(|c| c.is_ascii_digit()) {
+		Some(number)
+	} else {
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_number_formats() {
+		let point = DecimalMark::Point;
+		let cases = [
+			("5", 5.0),
+			("-3.5", -3.5),
+			("3e8", 3e8),
+			("1,500", 1500.0),
+			("1,500,000", 1500000.0),
+			("1,500.25", 1500.25),
+			("1.500,25", 1500.25),
+			("1.500.000", 1500000.0),
+			("2,5", 2.5),
+			("1.500", 1.5),
+			("1/2", 0.5),
+			("-3/4", -0.75),
+			("½", 0.5),
+			("1½", 1.5),
+			("-2¼", -2.25),
+			("1.5k", 1500.0),
+			("2K", 2000.0),
+			("1,5k", 1500.0),
+		];
+		for (word, expected) in cases {
+			assert_eq!(parse_number(word, point), Ok(expected), "{}", word);
+		}
+	}
+
+	#[test]
+	fn decimal_mark_decides_ambiguous_commas() {
+		assert_eq!(parse_number("1,500", DecimalMark::Comma), Ok(1.5));
+		assert_eq!(parse_number("1.500", DecimalMark::Comma), Ok(1500.0));
+		assert_eq!(parse_number("2,5", DecimalMark::Comma), Ok(2.5));
+		assert_eq!(parse_number("1,500.5", DecimalMark::Comma), Ok(1500.5));
+	}
+
+	#[test]
+	fn rejects_malformed_numbers() {
+		for word in [
+			"",
+			"k",
+			"nan",
+			"inf",
+			"1,50,0",
+			"1/0",
+			"1//2",
+			"1.2.3,4",
+			"12,34,567",
+			"½½",
+			"a½",
+			"1,,5",
+			",5",
+		] {
+			assert!(
+				parse_number(word, DecimalMark::Point).is_err(),
+				"{} should not be a number",
+				word
+			);
+		}
+	}
+}
@@ -0,0 +1,218 @@
+use super::{error::ConversionError, registry::Unit};
+
+// exponents of the SI base dimensions a quantity is measured in: length, mass, time and temperature,
+// a speed is [1, 0, -1, 0] because it's a length divided by a time
+pub type Dimensions = [i8; 4];
+
+pub const DIMENSIONLESS: Dimensions = [0; 4];
+
+const BASE_SYMBOLS: [&str; 4] = ["m", "kg", "s", "K"];
+
+// the dimensions units.def may use, every unit's factor converts into the SI unit of its dimensions
+const DIMENSIONS: &[(&str, Dimensions)] = &[
+	("length", [1, 0, 0, 0]),
+	("area", [2, 0, 0, 0]),
+	// distance per volume and volume per distance, mpg and L/100km
+	("fuel economy", [-2, 0, 0, 0]),
+	("fuel consumption", [2, 0, 0, 0]),
+	("volume", [3, 0, 0, 0]),
+	("mass", [0, 1, 0, 0]),
+	("time", [0, 0, 1, 0]),
+	("frequency", [0, 0, -1, 0]),
+	("speed", [1, 0, -1, 0]),
+	("force", [1, 1, -2, 0]),
+	("pressure", [-1, 1, -2, 0]),
+	("energy", [2, 1, -2, 0]),
+	("power", [2, 1, -3, 0]),
+	("temperature difference", [0, 0, 0, 1]),
+	("temperature", [0, 0, 0, 1]),
+];
+
+pub fn dimensions_named(name: &str) -> Option<Dimensions> {
+	DIMENSIONS
+		.iter()
+		.find(|&&(known, _)| known == name)
+		.map(|&(_, dimensions)| dimensions)
+}
+
+// the name of some dimensions for messages, "speed", or "m·s^-2" when they have no name
+pub fn describe(dimensions: Dimensions) -> String {
+	if let Some((name, _)) = DIMENSIONS.iter().find(|&&(_, known)| known == dimensions) {
+		return name.to_string();
+	}
+	if dimensions == DIMENSIONLESS {
+		return String::from("a plain number");
+	}
+	base_units(dimensions)
+}
+
+// the SI base units of some dimensions, "m·s^-1" for a speed
+pub fn base_units(dimensions: Dimensions) -> String {
+	BASE_SYMBOLS
+		.iter()
+		.zip(dimensions)
+		.filter(|&(_, exponent)| exponent != 0)
+		.map(|(symbol, exponent)| match exponent {
+			1 => symbol.to_string(),
+			exponent => format!("{}^{}", symbol, exponent),
+		})
+		.collect::<Vec<_>>()
+		.join("·")
+}
+
+// a value in the SI unit of its dimensions, "5 km" is 5000 with the dimensions of a length
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quantity {
+	pub value: f64,
+	pub dimensions: Dimensions,
+}
+
+impl Quantity {
+	pub fn number(value: f64) -> Self {
+		Quantity {
+			value,
+			dimensions: DIMENSIONLESS,
+		}
+	}
+
+	pub fn of(value: f64, unit: &Unit) -> Self {
+		Quantity {
+			value: unit.to_canonical(value),
+			dimensions: unit.dimensions,
+		}
+	}
+
+	// the value in `unit`, which has to measure the same dimensions
+	pub fn value_in(self, unit: &Unit) -> Result<f64, ConversionError> {
+		match self.value_per(Quantity::of(1.0, unit)) {
+			// named after the unit, L/100km measure a fuel consumption rather than an area
+			Err(ConversionError::MismatchedDimensions(dimensions, _)) => Err(ConversionError::MismatchedDimensions(
+				dimensions,
+				unit.dimension.clone(),
+			)),
+			result => result.map(|_| unit.to_unit(self.value)),
+		}
+	}
+
+	// how many of `unit` fit into the quantity, "5 km" is 5000 of "1 m"
+	pub fn value_per(self, unit: Quantity) -> Result<f64, ConversionError> {
+		if self.dimensions != unit.dimensions {
+			return Err(ConversionError::MismatchedDimensions(
+				describe(self.dimensions),
+				describe(unit.dimensions),
+			));
+		}
+		Ok(self.value / unit.value)
+	}
+
+	pub fn add(self, other: Quantity) -> Result<Quantity, ConversionError> {
+		if self.dimensions != other.dimensions {
+			return Err(ConversionError::MismatchedDimensions(
+				describe(self.dimensions),
+				describe(other.dimensions),
+			));
+		}
+		Ok(Quantity {
+			value: self.value + other.value,
+			..self
+		})
+	}
+
+	pub fn negate(self) -> Quantity {
+		Quantity {
+			value: -self.value,
+			..self
+		}
+	}
+
+	pub fn multiply(self, other: Quantity) -> Quantity {
+		let mut dimensions = self.dimensions;
+		for (dimension, other) in dimensions.iter_mut().zip(other.dimensions) {
+			*dimension = dimension.saturating_add(other);
+		}
+		Quantity {
+			value: self.value * other.value,
+			dimensions,
+		}
+	}
+
+	pub fn divide(self, other: Quantity) -> Result<Quantity, ConversionError> {
+		if other.value == 0.0 {
+			return Err(ConversionError::DivisionByZero);
+		}
+		Ok(self.multiply(other.power(-1)))
+	}
+
+	pub fn power(self, exponent: i8) -> Quantity {
+		Quantity {
+			value: self.value.powi(exponent.into()),
+			dimensions: self
+				.dimensions
+				.map(|dimension| dimension.saturating_mul(exponent)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::conversion::registry::registry;
+
+	fn quantity(value: f64, name: &str) -> Quantity {
+		Quantity::of(value, registry().get(name).unwrap())
+	}
+
+	#[test]
+	fn derived_dimensions_fall_out() {
+		let speed = quantity(100.0, "kilometer")
+			.divide(quantity(2.0, "hour"))
+			.unwrap();
+		assert_eq!(speed.dimensions, dimensions_named("speed").unwrap());
+		assert_eq!(
+			speed.value_in(registry().get("kilometer per hour").unwrap()),
+			Ok(50.0)
+		);
+		let area = quantity(3.0, "meter").multiply(quantity(4.0, "meter"));
+		assert_eq!(area.dimensions, dimensions_named("area").unwrap());
+		assert_eq!(area.power(-1).dimensions, [-2, 0, 0, 0]);
+		assert_eq!(
+			quantity(1.0, "kilowatt hour")
+				.divide(quantity(1.0, "hour"))
+				.unwrap()
+				.dimensions,
+			dimensions_named("power").unwrap()
+		);
+	}
+
+	#[test]
+	fn mismatched_dimensions() {
+		assert_eq!(
+			quantity(1.0, "meter").add(quantity(1.0, "kilogram")),
+			Err(ConversionError::MismatchedDimensions(
+				String::from("length"),
+				String::from("mass")
+			))
+		);
+		assert!(quantity(1.0, "meter")
+			.value_in(registry().get("second").unwrap())
+			.is_err());
+		assert_eq!(
+			quantity(1.0, "meter").value_in(registry().get("liter per 100 km").unwrap()),
+			Err(ConversionError::MismatchedDimensions(
+				String::from("length"),
+				String::from("fuel consumption")
+			))
+		);
+		assert_eq!(
+			quantity(1.0, "meter").divide(Quantity::number(0.0)),
+			Err(ConversionError::DivisionByZero)
+		);
+	}
+
+	#[test]
+	fn describes_dimensions() {
+		assert_eq!(describe([1, 0, -1, 0]), "speed");
+		assert_eq!(describe(DIMENSIONLESS), "a plain number");
+		assert_eq!(describe([1, 0, -2, 0]), "m·s^-2");
+	}
+}
@@ -0,0 +1,246 @@
+use super::{
+	error::ConversionError, format_measurement, number, parse_value, read_quantity, tokenize, ConversionOptions,
+	DecimalMark,
+};
+
+pub const RECIPE_USAGE: &str =
+	"`!recipe scale [x<factor> | serves <from> → <to>]` followed by one ingredient per line, e.g. `!recipe scale x1.5`";
+
+// "x1.5", "×2", "1.5x", "serves 4 → 6" or "4 -> 6", None if the line isn't a scale factor
+fn parse_factor(line: &str, decimal_mark: DecimalMark) -> Option<f64> {
+	let line = line.trim().to_lowercase();
+	let number = |word: &str| number::parse_number(word.trim(), decimal_mark).ok();
+	let servings = line
+		.strip_prefix("serves")
+		.or_else(|| line.strip_prefix("servings"))
+		.unwrap_or(&line);
+	if let Some((from, to)) = ["→", "->", " to "]
+		.iter()
+		.find_map(|separator| servings.split_once(separator))
+	{
+		return Some(number(to)? / number(from)?);
+	}
+	line.strip_prefix(['x', '×', '*'])
+		.or_else(|| line.strip_suffix(['x', '×']))
+		.and_then(number)
+}
+
+// amounts in a recipe are short, "1.5 cups" and "0.33 tsp" rather than "1.50" and "0.3333333333333333"
+fn format_amount(value: f64) -> String {
+	let rounded = format!("{:.2}", value);
+	rounded
+		.trim_end_matches('0')
+		.trim_end_matches('.')
+		.to_string()
+}
+
+// the text of `line` after its first `count` words, as it was written
+fn rest_of_line<'a>(line: &'a str, words: &[String], count: usize) -> &'a str {
+	let mut offset = 0;
+	for word in &words[..count] {
+		match line[offset..].find(word.as_str()) {
+			Some(start) => offset += start + word.len(),
+			None => return "",
+		}
+	}
+	let rest = line[offset..].trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+	// "2 cups of flour" becomes "250 grams flour"
+	match rest.split_once(char::is_whitespace) {
+		Some((of, rest)) if of.eq_ignore_ascii_case("of") => rest.trim_start(),
+		_ => rest,
+	}
+}
+
+// scales and converts the amount a line of a recipe starts with, None for lines without one
+fn scale_line(line: &str, factor: f64, options: &ConversionOptions) -> Option<(String, String)> {
+	let line = line.trim().trim_start_matches(['-', '*', '•']).trim_start();
+	let msg = tokenize(line);
+	if msg.is_empty() {
+		return None;
+	}
+	let words: Vec<_> = msg.iter().map(|word| word.to_lowercase()).collect();
+	// read like any message, so "2-3 cups" is a range and "~2 cups" an approximate amount
+	let reading = (1..msg.len())
+		.find_map(|i| read_quantity(&msg, &words, i, options, false))
+		.filter(|reading| reading.first == usize::from(reading.measurements[0].approximate));
+	let reading = match reading {
+		Some(reading) => reading,
+		None => {
+			// "3 eggs"
+			let (value, count) = parse_value(&msg, options.decimal_mark).ok()?;
+			let rest = rest_of_line(line, &msg, count);
+			return Some((format_amount(value * factor), rest.to_string()));
+		}
+	};
+	let mut measurement = reading.measurements.into_iter().next()?;
+	// oven temperatures and baking times stay as they are
+	if measurement.unit.offset == 0.0 && measurement.unit.counterpart.is_some() {
+		measurement.value *= factor;
+		measurement.low = measurement.low.map(|low| low * factor);
+	}
+	let converted = measurement.convert_readably(options.system);
+	let amount = format_measurement(
+		converted.as_ref().unwrap_or(&measurement),
+		true,
+		format_amount,
+	);
+	let rest = match rest_of_line(line, &msg, reading.end) {
+		// units like "sticks of butter" name the ingredient themselves,
+		// "1 lb 4 oz" adds up in ounces, so it's written out again as pounds and ounces
+		"" if converted.is_some() => {
+			let note = format_measurement(&measurement, measurement.compound, format_amount);
+			let written = msg[reading.unit..reading.unit + reading.unit_len].join(" ");
+			match note.strip_suffix(measurement.unit.symbol.as_str()) {
+				Some(amount) if !measurement.compound => format!("({}{})", amount, written),
+				_ => format!("({})", note),
+			}
+		}
+		rest => rest.to_string(),
+	};
+	Some((amount, rest))
+}
+
+// handles the arguments of `!recipe scale`: an optional factor on the first line and an ingredient on each other
+pub fn scale_recipe(args: &str, options: &ConversionOptions) -> Result<String, ConversionError> {
+	let mut lines: Vec<_> = args.lines().collect();
+	let factor = match lines
+		.first()
+		.and_then(|line| parse_factor(line, options.decimal_mark))
+	{
+		Some(factor) if factor.is_finite() && factor > 0.0 => {
+			lines.remove(0);
+			factor
+		}
+		Some(_) => return Err(ConversionError::InvalidNumber(lines[0].trim().to_string())),
+		None => 1.0,
+	};
+	while lines.first().is_some_and(|line| line.trim().is_empty()) {
+		lines.remove(0);
+	}
+	while lines.last().is_some_and(|line| line.trim().is_empty()) {
+		lines.pop();
+	}
+	if lines.is_empty() {
+		return Err(ConversionError::Usage(RECIPE_USAGE));
+	}
+	let scaled: Vec<_> = lines
+		.iter()
+		.map(|line| scale_line(line, factor, options))
+		.collect();
+	let width = scaled
+		.iter()
+		.flatten()
+		.map(|(amount, _)| amount.chars().count())
+		.max()
+		.unwrap_or(0);
+	// amounts in a column of their own, lines without one keep their text in the ingredient column
+	let list: Vec<_> = lines
+		.iter()
+		.zip(&scaled)
+		.map(|(line, scaled)| match scaled {
+			Some((amount, rest)) => format!("{:<width$}  {}", amount, rest, width = width),
+			None if line.trim().is_empty() => String::new(),
+			None => format!("{:<width$}  {}", "", line.trim(), width = width),
+		})
+		.map(|line| line.trim_end().to_string())
+		.collect();
+	let heading = if factor == 1.0 {
+		String::new()
+	} else {
+		format!("Scaled by {}:\n", format_amount(factor))
+	};
+	Ok(format!("{}```\n{}\n```", heading, list.join("\n")))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::conversion::PreferredSystem;
+
+	fn factor(line: &str) -> Option<f64> {
+		parse_factor(line, DecimalMark::Point)
+	}
+
+	#[test]
+	fn parses_scale_factors() {
+		assert_eq!(factor("x1.5"), Some(1.5));
+		assert_eq!(factor("×2"), Some(2.0));
+		assert_eq!(factor("0.5X"), Some(0.5));
+		assert_eq!(factor("Serves 4 → 6"), Some(1.5));
+		assert_eq!(factor("4 -> 2"), Some(0.5));
+		assert_eq!(factor("servings 2 to 3"), Some(1.5));
+		assert_eq!(factor("2 cups flour"), None);
+		assert_eq!(factor("1 to 2 cups flour"), None);
+		assert_eq!(factor("xanthan gum"), None);
+		assert_eq!(factor(""), None);
+	}
+
+	#[test]
+	fn scales_and_converts_recipes() {
+		let recipe = "serves 4 → 6\n- 2 cups of flour, sifted\n- 1 stick butter\n3 eggs\n1 tsp salt\nvanilla to taste\n\nBake at 350 f";
+		assert_eq!(
+			scale_recipe(recipe, &ConversionOptions::default()),
+			Ok(String::from(
+				"Scaled by 1.5:\n```\n374.76 grams  flour, sifted\n170.1 grams   (1.5 stick butter)\n4.5           eggs\n9.02 grams    salt\n              vanilla to taste\n\n              Bake at 350 f\n```"
+			))
+		);
+	}
+
+	#[test]
+	fn notes_compounds_as_written() {
+		assert_eq!(
+			scale_recipe("x2\n1 lb 4 oz\n5 ft 11 in", &ConversionOptions::default()),
+			Ok(String::from(
+				"Scaled by 2:\n```\n1133.98 grams  (2 lbs 8 oz)\n360.68 cm      (11 ft 10 in)\n```"
+			))
+		);
+	}
+
+	#[test]
+	fn scales_ranges_and_rough_amounts() {
+		assert_eq!(
+			scale_recipe("x2\n2-3 cups flour\n~2 cups sugar\n1 to 2 tsp salt", &ConversionOptions::default()),
+			Ok(String::from(
+				"Scaled by 2:\n```\n499.67–749.51 grams  flour\n~799.67 grams        sugar\n12.03–24.05 grams    salt\n```"
+			))
+		);
+	}
+
+	#[test]
+	fn reads_c_as_a_count_in_front_of_ingredients() {
+		assert_eq!(
+			scale_recipe("x3\n1 c flour", &ConversionOptions::default()),
+			Ok(String::from("Scaled by 3:\n```\n3  c flour\n```"))
+		);
+	}
+
+	#[test]
+	fn keeps_familiar_units() {
+		let options = ConversionOptions {
+			system: PreferredSystem::Us,
+			..ConversionOptions::default()
+		};
+		assert_eq!(
+			scale_recipe("x2\n1 ½ cups sugar\n200 g butter", &options),
+			Ok(String::from(
+				"Scaled by 2:\n```\n3 cups    sugar\n14.11 oz  butter\n```"
+			))
+		);
+	}
+
+	#[test]
+	fn needs_a_recipe() {
+		assert_eq!(
+			scale_recipe("serves 0 → 6\n1 cup flour", &ConversionOptions::default()),
+			Err(ConversionError::InvalidNumber(String::from("serves 0 → 6")))
+		);
+		assert_eq!(
+			scale_recipe("", &ConversionOptions::default()),
+			Err(ConversionError::Usage(RECIPE_USAGE))
+		);
+		assert_eq!(
+			scale_recipe("x2\n\n", &ConversionOptions::default()),
+			Err(ConversionError::Usage(RECIPE_USAGE))
+		);
+	}
+}
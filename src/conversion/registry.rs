@@ -0,0 +1,516 @@
+use std::{
+	collections::{HashMap, HashSet},
+	sync::LazyLock,
+};
+
+use super::{
+	quantity::{self, Dimensions},
+	system::UnitSystem,
+};
+
+static REGISTRY: LazyLock<Registry> =
+	LazyLock::new(|| Registry::parse(include_str!("units.def")).expect("units.def contains invalid definitions"));
+
+pub fn registry() -> &'static Registry {
+	&REGISTRY
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Unit {
+	pub dimension: String,
+	// what the dimension is made of, a speed is a length per time
+	pub dimensions: Dimensions,
+	pub name: String,
+	pub symbol: String,
+	pub system: UnitSystem,
+	pub factor: f64,
+	pub offset: f64,
+	// None for units that are only converted on request
+	pub counterpart: Option<String>,
+	pub aliases: Vec<String>,
+}
+
+impl Unit {
+	pub fn to_canonical(&self, val: f64) -> f64 {
+		val * self.factor + self.offset
+	}
+
+	pub fn to_unit(&self, val: f64) -> f64 {
+		(val - self.offset) / self.factor
+	}
+}
+
+#[derive(Debug)]
+pub struct Registry {
+	units: Vec<Unit>,
+	by_name: HashMap<String, usize>,
+	by_alias: HashMap<String, Vec<usize>>,
+	// aliases only recognised as the smaller part of a compound, like the "in" of "6 ft 2 in"
+	by_part_alias: HashMap<String, Vec<usize>>,
+	// aliases that often mean something else ("c", "ft"), they need more evidence to be converted
+	ambiguous: HashSet<String>,
+	// aliases that only match when written exactly the same way ("Cal", "mb"),
+	// all other aliases are stored lowercase and match regardless of case ("Kilometers")
+	case_sensitive: HashSet<String>,
+	compounds: Vec<Vec<usize>>,
+	// dimensions that are each other's inverse, fuel economy in mpg and fuel consumption in L/100km
+	reciprocals: Vec<(String, String)>,
+	// every unit of the si system with its prefixed versions, "meter" with "kilometer" and "millimeter"
+	family: HashMap<usize, Vec<usize>>,
+	longest_alias: usize,
+}
+
+// an SI prefix, "µ" and "μ" are both written for micro
+struct Prefix {
+	name: String,
+	symbols: Vec<String>,
+	factor: f64,
+}
+
+struct Alias {
+	name: String,
+	part: bool,
+	ambiguous: bool,
+	case_sensitive: bool,
+}
+
+// strips the markup of an alias in units.def: "(in)" is only part of a compound, "c?" is ambiguous
+// and "=Cal" is case-sensitive
+fn parse_alias(alias: &str) -> Alias {
+	let (alias, case_sensitive) = match alias.strip_prefix('=') {
+		Some(alias) => (alias, true),
+		None => (alias, false),
+	};
+	let (alias, ambiguous) = match alias.strip_suffix('?') {
+		Some(alias) => (alias, true),
+		None => (alias, false),
+	};
+	let (alias, part) = match alias
+		.strip_prefix('(')
+		.and_then(|alias| alias.strip_suffix(')'))
+	{
+		Some(alias) => (alias, true),
+		None => (alias, false),
+	};
+	Alias {
+		name: if case_sensitive {
+			alias.to_string()
+		} else {
+			alias.to_lowercase()
+		},
+		part,
+		ambiguous,
+		case_sensitive,
+	}
+}
+
+impl Registry {
+	pub fn parse(definitions: &str) -> Result<Registry, String> {
+		let mut registry = Registry {
+			units: vec![],
+			by_name: HashMap::new(),
+			by_alias: HashMap::new(),
+			by_part_alias: HashMap::new(),
+			ambiguous: HashSet::new(),
+			case_sensitive: HashSet::new(),
+			compounds: vec![],
+			reciprocals: vec![],
+			family: HashMap::new(),
+			longest_alias: 1,
+		};
+		let mut compounds = vec![];
+		let mut prefixes = vec![];
+		for (line_no, line) in definitions.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let fields: Vec<_> = line.split('|').map(str::trim).collect();
+			let number = |field: &str| {
+				field
+					.parse::<f64>()
+					.map_err(|_| format!("line {}: '{}' is not a number", line_no + 1, field))
+			};
+			if fields[0] == "compound" && fields.len() == 2 {
+				compounds.push((line_no, fields[1]));
+				continue;
+			}
+			if fields[0] == "reciprocal" && fields.len() == 2 {
+				let (dimension, inverse) = fields[1]
+					.split_once(',')
+					.map(|(dimension, inverse)| (dimension.trim(), inverse.trim()))
+					.ok_or_else(|| format!("line {}: a reciprocal needs two dimensions", line_no + 1))?;
+				match (
+					quantity::dimensions_named(dimension),
+					quantity::dimensions_named(inverse),
+				) {
+					(Some(a), Some(b)) if a.iter().zip(b).all(|(a, b)| a + b == 0) => {}
+					_ => {
+						return Err(format!(
+							"line {}: '{}' and '{}' aren't each other's inverse",
+							line_no + 1,
+							dimension,
+							inverse
+						))
+					}
+				}
+				registry
+					.reciprocals
+					.push((dimension.to_string(), inverse.to_string()));
+				continue;
+			}
+			if fields[0] == "prefix" && fields.len() == 4 {
+				prefixes.push(Prefix {
+					name: fields[1].to_string(),
+					symbols: fields[2]
+						.split(',')
+						.map(|symbol| symbol.trim().to_string())
+						.collect(),
+					factor: number(fields[3])?,
+				});
+				continue;
+			}
+			if fields.len() != 8 {
+				return Err(format!(
+					"line {}: expected 8 fields, found {}",
+					line_no + 1,
+					fields.len()
+				));
+			}
+			let aliases: Vec<_> = fields[7]
+				.split(',')
+				.map(|alias| parse_alias(alias.trim()))
+				.collect();
+			let dimensions = quantity::dimensions_named(fields[0])
+				.ok_or_else(|| format!("line {}: '{}' is not a dimension", line_no + 1, fields[0]))?;
+			let unit = Unit {
+				dimension: fields[0].to_string(),
+				dimensions,
+				name: fields[1].to_string(),
+				symbol: fields[2].to_string(),
+				system: fields[3]
+					.parse()
+					.map_err(|why| format!("line {}: {}", line_no + 1, why))?,
+				factor: number(fields[4])?,
+				offset: number(fields[5])?,
+				counterpart: match fields[6] {
+					"-" => None,
+					counterpart => Some(counterpart.to_string()),
+				},
+				aliases: aliases.iter().map(|alias| alias.name.clone()).collect(),
+			};
+			registry
+				.add_unit(unit, aliases)
+				.map_err(|why| format!("line {}: {}", line_no + 1, why))?;
+		}
+		registry.add_prefixed_units(&prefixes)?;
+		// every counterpart has to exist and measure the same thing or its inverse
+		for unit in &registry.units {
+			let counterpart = match &unit.counterpart {
+				Some(counterpart) => counterpart,
+				None => continue,
+			};
+			match registry.get(counterpart) {
+				Some(counterpart)
+					if counterpart.dimension == unit.dimension || registry.reciprocal(unit, counterpart) => {}
+				Some(_) => {
+					return Err(format!(
+						"{}: counterpart '{}' is a different dimension",
+						unit.name, counterpart
+					))
+				}
+				None => {
+					return Err(format!(
+						"{}: counterpart '{}' is not defined",
+						unit.name, counterpart
+					))
+				}
+			}
+		}
+		for (line_no, names) in compounds {
+			let mut compound = vec![];
+			for name in names.split(',').map(str::trim) {
+				let index = *registry
+					.by_name
+					.get(name)
+					.ok_or_else(|| format!("line {}: unit '{}' is not defined", line_no + 1, name))?;
+				compound.push(index);
+			}
+			let head = &registry.units[compound[0]];
+			if compound.len() < 2
+				|| compound.iter().any(|&i| {
+					let unit = &registry.units[i];
+					unit.dimension != head.dimension || unit.offset != 0.0
+				}) {
+				return Err(format!(
+					"line {}: a compound needs at least two units of one dimension without an offset",
+					line_no + 1
+				));
+			}
+			registry.compounds.push(compound);
+		}
+		Ok(registry)
+	}
+
+	fn add_unit(&mut self, unit: Unit, aliases: Vec<Alias>) -> Result<(), String> {
+		let index = self.units.len();
+		if self.by_name.insert(unit.name.clone(), index).is_some() {
+			return Err(format!("unit '{}' is defined twice", unit.name));
+		}
+		for alias in aliases {
+			let Alias {
+				name: alias,
+				part,
+				ambiguous,
+				case_sensitive,
+			} = alias;
+			let defined = self.by_alias.contains_key(&alias) || self.by_part_alias.contains_key(&alias);
+			if defined && self.case_sensitive.contains(&alias) != case_sensitive {
+				return Err(format!("alias '{}' is both case-sensitive and not", alias));
+			}
+			if ambiguous {
+				self.ambiguous.insert(alias.clone());
+			}
+			if case_sensitive {
+				self.case_sensitive.insert(alias.clone());
+			}
+			let aliases = if part {
+				&mut self.by_part_alias
+			} else {
+				&mut self.by_alias
+			};
+			// an alias may be shared by variants of the same unit (US and imperial gallons),
+			// the unit defined first is the default interpretation
+			let units = &self.units;
+			let indices = aliases.entry(alias.clone()).or_default();
+			if indices
+				.iter()
+				.any(|&i| units[i].dimension != unit.dimension)
+			{
+				return Err(format!(
+					"alias '{}' is already taken by another dimension",
+					alias
+				));
+			}
+			indices.push(index);
+			self.longest_alias = self.longest_alias.max(alias.split_whitespace().count());
+		}
+		self.units.push(unit);
+		Ok(())
+	}
+
+	// combines every prefix with every unit of the si system: "mm" and "millimeters" from "=m" and "meters",
+	// units that are defined explicitly ("kilometer") keep their own definition
+	fn add_prefixed_units(&mut self, prefixes: &[Prefix]) -> Result<(), String> {
+		let bases: Vec<_> = (0..self.units.len())
+			.filter(|&i| self.units[i].system == UnitSystem::Si)
+			.collect();
+		for base_index in bases {
+			let mut family = vec![base_index];
+			for prefix in prefixes {
+				let base = &self.units[base_index];
+				let name = format!("{}{}", prefix.name, base.name);
+				if let Some(&index) = self.by_name.get(&name) {
+					family.push(index);
+					continue;
+				}
+				if base.offset != 0.0 {
+					return Err(format!(
+						"{}: units with an offset can't take prefixes",
+						base.name
+					));
+				}
+				// the symbol that prefixes attach to is the first case-sensitive alias ("=m")
+				let symbol = base
+					.aliases
+					.iter()
+					.find(|alias| self.case_sensitive.contains(*alias))
+					.ok_or_else(|| {
+						format!(
+							"{}: a unit taking prefixes needs a case-sensitive symbol",
+							base.name
+						)
+					})?;
+				let mut aliases: Vec<_> = prefix
+					.symbols
+					.iter()
+					.map(|prefix| Alias {
+						name: format!("{}{}", prefix, symbol),
+						part: false,
+						ambiguous: false,
+						case_sensitive: true,
+					})
+					.collect();
+				// word forms get the prefix's name, "millimeter" and "millimetres"
+				aliases.extend(
+					base.aliases
+						.iter()
+						.filter(|alias| {
+							!self.case_sensitive.contains(*alias)
+								&& !self.ambiguous.contains(*alias)
+								&& alias.chars().all(char::is_alphabetic)
+						})
+						.map(|alias| Alias {
+							name: format!("{}{}", prefix.name, alias),
+							part: false,
+							ambiguous: false,
+							case_sensitive: false,
+						}),
+				);
+				let factor = base.factor * prefix.factor;
+				// converted to the closest unit of the US and imperial systems, a millimeter into inches,
+				// units that aren't even within ten thousand times of one ("nm") are only converted on request
+				let distance = |unit: &Unit| (unit.factor / factor).ln().abs();
+				let counterpart = self
+					.units_of(&base.dimension)
+					.filter(|unit| {
+						matches!(
+							unit.system,
+							UnitSystem::Us | UnitSystem::Imperial | UnitSystem::Customary
+						)
+					})
+					.min_by(|a, b| {
+						let imperial = |unit: &Unit| unit.system == UnitSystem::Imperial;
+						imperial(a)
+							.cmp(&imperial(b))
+							.then(distance(a).total_cmp(&distance(b)))
+					})
+					.filter(|unit| distance(unit) < 1e4_f64.ln())
+					.map(|unit| unit.name.clone());
+				let unit = Unit {
+					dimension: base.dimension.clone(),
+					dimensions: base.dimensions,
+					name,
+					symbol: aliases[0].name.clone(),
+					system: UnitSystem::Metric,
+					factor,
+					offset: 0.0,
+					counterpart,
+					aliases: aliases.iter().map(|alias| alias.name.clone()).collect(),
+				};
+				let base_name = base.name.clone();
+				family.push(self.units.len());
+				self.add_unit(unit, aliases)
+					.map_err(|why| format!("{}: {}", base_name, why))?;
+			}
+			for &index in &family {
+				self.family.insert(index, family.clone());
+			}
+		}
+		Ok(())
+	}
+
+	pub fn get(&self, name: &str) -> Option<&Unit> {
+		self.by_name.get(name).map(|&index| &self.units[index])
+	}
+
+	pub fn lookup_all<'a>(&'a self, alias: &str) -> impl Iterator<Item = &'a Unit> {
+		self.resolve(&self.by_alias, alias)
+			.into_iter()
+			.flatten()
+			.map(move |&index| &self.units[index])
+	}
+
+	// like lookup_all, but also accepts aliases that only make sense inside a compound
+	pub fn lookup_part<'a>(&'a self, alias: &str) -> impl Iterator<Item = &'a Unit> {
+		let part = self
+			.resolve(&self.by_part_alias, alias)
+			.into_iter()
+			.flatten();
+		self.resolve(&self.by_alias, alias)
+			.into_iter()
+			.flatten()
+			.chain(part)
+			.map(move |&index| &self.units[index])
+	}
+
+	pub fn is_ambiguous(&self, alias: &str) -> bool {
+		[&self.by_alias, &self.by_part_alias]
+			.iter()
+			.find_map(|aliases| self.resolve_key(aliases, alias))
+			.is_some_and(|alias| self.ambiguous.contains(alias))
+	}
+
+	// the alias of `aliases` a word written as `alias` refers to, case-sensitive aliases
+	// have to match exactly while all others are compared in lowercase
+	fn resolve_key<'a>(&self, aliases: &'a HashMap<String, Vec<usize>>, alias: &str) -> Option<&'a String> {
+		if self.case_sensitive.contains(alias) {
+			if let Some((key, _)) = aliases.get_key_value(alias) {
+				return Some(key);
+			}
+		}
+		let lowercase = alias.to_lowercase();
+		if self.case_sensitive.contains(&lowercase) {
+			return None;
+		}
+		aliases.get_key_value(&lowercase).map(|(key, _)| key)
+	}
+
+	fn resolve<'a>(&self, aliases: &'a HashMap<String, Vec<usize>>, alias: &str) -> Option<&'a Vec<usize>> {
+		self.resolve_key(aliases, alias).map(|key| &aliases[key])
+	}
+
+	// the compound ("ft in", "lbs oz") a unit belongs to, largest unit first
+	pub fn compound(&self, unit: &Unit) -> Option<Vec<&Unit>> {
+		let index = self.by_name.get(&unit.name)?;
+		self.compounds
+			.iter()
+			.find(|compound| compound.contains(index))
+			.map(|compound| compound.iter().map(|&i| &self.units[i]).collect())
+	}
+
+	// the unit with all its prefixed versions, if it takes SI prefixes
+	pub fn family(&self, unit: &Unit) -> Option<Vec<&Unit>> {
+		let index = self.by_name.get(&unit.name)?;
+		self.family
+			.get(index)
+			.map(|family| family.iter().map(|&i| &self.units[i]).collect())
+	}
+
+	// the unit measuring a difference on the scale of `unit`, "celsius difference" for "celsius"
+	pub fn difference(&self, unit: &Unit) -> Option<&Unit> {
+		self.get(&format!("{} difference", unit.name))
+	}
+
+	// whether values of `a` convert into `b` by taking their inverse, 5 L/100km are 47 mpg
+	pub fn reciprocal(&self, a: &Unit, b: &Unit) -> bool {
+		self.reciprocals.iter().any(|(dimension, inverse)| {
+			(a.dimension == *dimension && b.dimension == *inverse)
+				|| (a.dimension == *inverse && b.dimension == *dimension)
+		})
+	}
+
+	// whether `unit` measures one of two dimensions that are each other's inverse, mpg and L/100km do
+	pub fn has_reciprocal(&self, unit: &Unit) -> bool {
+		self.reciprocals
+			.iter()
+			.any(|(dimension, inverse)| unit.dimension == *dimension || unit.dimension == *inverse)
+	}
+
+	// number of words in the longest multi-word alias ("us fluid ounces")
+	pub fn longest_alias(&self) -> usize {
+		self.longest_alias
+	}
+
+	pub fn counterpart(&self, unit: &Unit) -> Option<&Unit> {
+		// checked when the registry was parsed
+		unit.counterpart
+			.as_ref()
+			.map(|counterpart| &self.units[self.by_name[counterpart]])
+	}
+
+	pub fn units(&self) -> impl Iterator<Item = &Unit> {
+		self.units.iter()
+	}
+
+	// every unit measuring `dimension`
+	pub fn units_of<'a>(&'a self, dimension: &'a str) -> impl Iterator<Item = &'a Unit> {
+		self.units
+			.iter()
+			.filter(move |unit| unit.dimension == dimension)
+	}
+
+	pub fn aliases(&self) -> impl Iterator<Item = &str> {
+		self.by_alias.keys().map(String::as_str)
+	}
+}
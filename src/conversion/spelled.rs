@@ -0,0 +1,240 @@
+// numbers written out in english, "five", "twenty-five", "a hundred and fifty" or "two and a half"
+
+const ONES: &[(&str, f64)] = &[
+	("zero", 0.0),
+	("one", 1.0),
+	("two", 2.0),
+	("three", 3.0),
+	("four", 4.0),
+	("five", 5.0),
+	("six", 6.0),
+	("seven", 7.0),
+	("eight", 8.0),
+	("nine", 9.0),
+	("ten", 10.0),
+	("eleven", 11.0),
+	("twelve", 12.0),
+	("thirteen", 13.0),
+	("fourteen", 14.0),
+	("fifteen", 15.0),
+	("sixteen", 16.0),
+	("seventeen", 17.0),
+	("eighteen", 18.0),
+	("nineteen", 19.0),
+];
+
+const TENS: &[(&str, f64)] = &[
+	("twenty", 20.0),
+	("thirty", 30.0),
+	("forty", 40.0),
+	("fifty", 50.0),
+	("sixty", 60.0),
+	("seventy", 70.0),
+	("eighty", 80.0),
+	("ninety", 90.0),
+];
+
+// "thousand" and bigger close a group, "two thousand three hundred"
+const SCALES: &[(&str, f64)] = &[("thousand", 1e3), ("million", 1e6), ("billion", 1e9)];
+
+// multiply whatever came before them, "three quarters", "half a dozen" or "a couple"
+const MULTIPLIERS: &[(&str, f64)] = &[
+	("couple", 2.0),
+	("dozen", 12.0),
+	("half", 1.0 / 2.0),
+	("halves", 1.0 / 2.0),
+	("third", 1.0 / 3.0),
+	("thirds", 1.0 / 3.0),
+	("quarter", 1.0 / 4.0),
+	("quarters", 1.0 / 4.0),
+];
+
+// the most words a spelled number is looked for in front of a unit
+pub const LONGEST_NUMBER: usize = 8;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Token {
+	Article,
+	Ones,
+	Tens,
+	Hundred,
+	Scale,
+	Multiplier,
+	Of,
+	And,
+}
+
+fn lookup(table: &[(&str, f64)], word: &str) -> Option<f64> {
+	table
+		.iter()
+		.find(|&&(name, _)| name == word)
+		.map(|&(_, value)| value)
+}
+
+// whether a word can only be part of a number, "a", "and" and "of" are too common to count
+pub fn is_number_word(word: &str) -> bool {
+	let word = word.to_lowercase();
+	word == "hundred"
+		|| [ONES, TENS, SCALES, MULTIPLIERS]
+			.iter()
+			.any(|table| lookup(table, &word).is_some())
+}
+
+// reads all of `words` as one number, None if any of them doesn't fit,
+// a lone "a" isn't a number because "a foot" is much more often a body part than a length
+pub fn parse_spelled(words: &[String]) -> Option<f64> {
+	let mut total = 0.0;
+	let mut current: Option<f64> = None;
+	let mut previous: Option<Token> = None;
+	let mut counted = false;
+	// "and" after a plain number only leads to a fraction, "five and ten" are two numbers
+	let mut fraction = false;
+	let tokens = words
+		.iter()
+		.flat_map(|word| word.split('-'))
+		.map(str::to_lowercase);
+	for token in tokens {
+		let kind = if let Some(value) = lookup(ONES, &token) {
+			if matches!(
+				previous,
+				Some(Token::Ones) | Some(Token::Article) | Some(Token::Multiplier)
+			) {
+				return None;
+			}
+			current = Some(current.unwrap_or(0.0) + value);
+			Token::Ones
+		} else if let Some(value) = lookup(TENS, &token) {
+			if matches!(
+				previous,
+				Some(Token::Ones) | Some(Token::Tens) | Some(Token::Article) | Some(Token::Multiplier)
+			) {
+				return None;
+			}
+			current = Some(current.unwrap_or(0.0) + value);
+			Token::Tens
+		} else if token == "hundred" {
+			if matches!(previous, Some(Token::Hundred) | Some(Token::Multiplier)) {
+				return None;
+			}
+			current = Some(current.unwrap_or(1.0) * 100.0);
+			Token::Hundred
+		} else if let Some(value) = lookup(SCALES, &token) {
+			if matches!(previous, Some(Token::Scale) | Some(Token::And)) {
+				return None;
+			}
+			total += current.take().unwrap_or(1.0) * value;
+			Token::Scale
+		} else if let Some(value) = lookup(MULTIPLIERS, &token) {
+			if previous == Some(Token::Multiplier) && value < 1.0 {
+				return None;
+			}
+			current = Some(current.unwrap_or(1.0) * value);
+			Token::Multiplier
+		} else {
+			match token.as_str() {
+				// "a hundred", and the filler in "half a dozen" or "a quarter of a"
+				"a" | "an" => {
+					if current.is_some() && !matches!(previous, Some(Token::Multiplier) | Some(Token::Of)) {
+						return None;
+					}
+					current.get_or_insert(1.0);
+					Token::Article
+				}
+				"of" if matches!(previous, Some(Token::Multiplier)) => Token::Of,
+				// "one hundred and fifty" or "two and a half"
+				"and" if previous.is_some_and(|previous| previous != Token::And && previous != Token::Of) => {
+					fraction = !matches!(previous, Some(Token::Hundred) | Some(Token::Scale));
+					total += current.take().unwrap_or(0.0);
+					Token::And
+				}
+				_ => return None,
+			}
+		};
+		counted |= !matches!(kind, Token::Article | Token::Of | Token::And);
+		previous = Some(kind);
+	}
+	if !counted || previous == Some(Token::And) || fraction && current.unwrap_or(0.0) >= 1.0 {
+		return None;
+	}
+	Some(total + current.unwrap_or(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn spelled(phrase: &str) -> Option<f64> {
+		let words: Vec<_> = phrase.split_whitespace().map(String::from).collect();
+		parse_spelled(&words)
+	}
+
+	#[test]
+	fn parses_spelled_numbers() {
+		let cases = [
+			("five", 5.0),
+			("Twelve", 12.0),
+			("twenty five", 25.0),
+			("twenty-five", 25.0),
+			("a hundred", 100.0),
+			("one hundred and fifty", 150.0),
+			("one hundred and five", 105.0),
+			("two thousand and five", 2005.0),
+			("two thousand three hundred", 2300.0),
+			("one million five hundred thousand", 1_500_000.0),
+			("half", 0.5),
+			("half a", 0.5),
+			("half an", 0.5),
+			("a half", 0.5),
+			("two and a half", 2.5),
+			("one and three quarters", 1.75),
+			("three quarters of a", 0.75),
+			("a quarter of an", 0.25),
+			("a third of a", 1.0 / 3.0),
+			("a couple", 2.0),
+			("a couple of", 2.0),
+			("a dozen", 12.0),
+			("two dozen", 24.0),
+			("half a dozen", 6.0),
+		];
+		for (phrase, expected) in cases {
+			assert_eq!(spelled(phrase), Some(expected), "{}", phrase);
+		}
+	}
+
+	#[test]
+	fn rejects_words_that_arent_numbers() {
+		for phrase in [
+			"",
+			"a",
+			"an",
+			"and",
+			"of",
+			"the",
+			"a a",
+			"five five",
+			"five twenty",
+			"two and",
+			"five and ten",
+			"two and five",
+			"twenty and a hundred",
+			"and two",
+			"one of",
+			"a five",
+			"half half",
+			"million million",
+			"five apples",
+		] {
+			assert_eq!(spelled(phrase), None, "{} should not be a number", phrase);
+		}
+	}
+
+	#[test]
+	fn number_words() {
+		assert!(is_number_word("Five"));
+		assert!(is_number_word("hundred"));
+		assert!(is_number_word("half"));
+		assert!(!is_number_word("a"));
+		assert!(!is_number_word("and"));
+		assert!(!is_number_word("mile"));
+	}
+}
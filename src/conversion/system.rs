@@ -0,0 +1,86 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+// the measurement system a unit belongs to, customary units are shared by the US and imperial systems,
+// si units are metric units that take SI prefixes and universal units are used everywhere ("kcal")
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitSystem {
+	Metric,
+	Si,
+	Us,
+	Imperial,
+	Customary,
+	Universal,
+}
+
+impl FromStr for UnitSystem {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"metric" => Ok(UnitSystem::Metric),
+			"si" => Ok(UnitSystem::Si),
+			"us" => Ok(UnitSystem::Us),
+			"imperial" => Ok(UnitSystem::Imperial),
+			"customary" => Ok(UnitSystem::Customary),
+			"universal" => Ok(UnitSystem::Universal),
+			_ => Err(format!("'{}' is not a unit system", s)),
+		}
+	}
+}
+
+// the units an audience is used to, "both" converts metric and customary units into each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PreferredSystem {
+	#[default]
+	Both,
+	Metric,
+	Us,
+	Imperial,
+}
+
+impl PreferredSystem {
+	// whether units of `system` need no conversion for this audience, nothing is familiar to "both"
+	pub fn includes(self, system: UnitSystem) -> bool {
+		matches!(
+			(self, system),
+			(
+				PreferredSystem::Metric | PreferredSystem::Us | PreferredSystem::Imperial,
+				UnitSystem::Universal
+			) | (PreferredSystem::Metric, UnitSystem::Metric | UnitSystem::Si)
+				| (PreferredSystem::Us, UnitSystem::Us | UnitSystem::Customary)
+				| (
+					PreferredSystem::Imperial,
+					UnitSystem::Imperial | UnitSystem::Customary
+				)
+		)
+	}
+}
+
+impl FromStr for PreferredSystem {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"both" => Ok(PreferredSystem::Both),
+			"metric" => Ok(PreferredSystem::Metric),
+			"us" | "us customary" => Ok(PreferredSystem::Us),
+			"imperial" => Ok(PreferredSystem::Imperial),
+			_ => Err(format!("'{}' isn't metric, us, imperial or both", s)),
+		}
+	}
+}
+
+impl fmt::Display for PreferredSystem {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let name = match self {
+			PreferredSystem::Both => "both",
+			PreferredSystem::Metric => "metric",
+			PreferredSystem::Us => "us",
+			PreferredSystem::Imperial => "imperial",
+		};
+		write!(f, "{}", name)
+	}
+}
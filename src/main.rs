@@ -9,7 +9,12 @@ use serenity::{
 		},
 		StandardFramework,
 	},
-	model::{channel::Message, id::UserId, prelude::Ready},
+	model::{
+		channel::Message,
+		event::MessageUpdateEvent,
+		id::{ChannelId, GuildId, MessageId, UserId},
+		prelude::Ready,
+	},
 	prelude::{RwLock, TypeMapKey},
 	Client,
 };
@@ -22,8 +27,11 @@ use std::{
 
 mod birthdays;
 mod conversion;
+mod settings;
 
 use birthdays::*;
+use conversion::commands::*;
+use settings::*;
 
 struct TodayDate;
 impl TypeMapKey for TodayDate {
@@ -31,7 +39,19 @@ impl TypeMapKey for TodayDate {
 }
 
 #[group]
-#[commands(about, update_db, add_birthday, print_db, delete_birthday)]
+#[commands(
+	about,
+	convert,
+	calc,
+	recipe,
+	strictness,
+	decimal,
+	units,
+	update_db,
+	add_birthday,
+	print_db,
+	delete_birthday
+)]
 struct General;
 
 struct Handler;
@@ -57,16 +77,61 @@ impl EventHandler for Handler {
 			notify_users(&ctx, &msg).await;
 		}
 		if !msg.author.bot {
-			if let Some(reply) = conversion::respond_to_msg(&msg.content) {
-				msg.reply(&ctx, reply).await.unwrap();
-				println!("{:?}", msg.timestamp.date())
+			if let Err(why) = send_conversion(&ctx, &msg).await {
+				println!("Failed to send a conversion: {:?}", why);
 			}
 		}
 	}
+	async fn message_update(
+		&self,
+		ctx: Context,
+		_old_if_available: Option<Message>,
+		_new: Option<Message>,
+		event: MessageUpdateEvent,
+	) {
+		// embeds being resolved also count as updates, only edits carry the new content
+		let content = match event.content {
+			Some(content) => content,
+			None => return,
+		};
+		if event.author.as_ref().is_some_and(|author| author.bot) {
+			return;
+		}
+		let author_id = event.author.map(|author| author.id);
+		if let Err(why) = update_conversion(
+			&ctx,
+			event.channel_id,
+			event.id,
+			event.guild_id,
+			author_id,
+			&content,
+		)
+		.await
+		{
+			println!("Failed to update a conversion: {:?}", why);
+		}
+	}
+	async fn message_delete(
+		&self,
+		ctx: Context,
+		channel_id: ChannelId,
+		deleted_message_id: MessageId,
+		_guild_id: Option<GuildId>,
+	) {
+		if let Err(why) = delete_conversion(&ctx, channel_id, deleted_message_id).await {
+			println!("Failed to delete a conversion: {:?}", why);
+		}
+	}
 	async fn ready(&self, ctx: Context, _ready: Ready) {
 		database_update(&ctx)
 			.await
 			.expect("failed to update database");
+		guild_settings_update(&ctx)
+			.await
+			.expect("failed to load guild settings");
+		user_settings_update(&ctx)
+			.await
+			.expect("failed to load user settings");
 	}
 }
 
@@ -77,7 +142,7 @@ async fn main() {
 	let mut owners = HashSet::new();
 	owners.insert(UserId::from(360433679111159808));
 	let framework = StandardFramework::new()
-		.configure(|c| c.prefix("!").owners(owners))
+		.configure(|c| c.prefix(conversion::COMMAND_PREFIX).owners(owners))
 		.group(&GENERAL_GROUP);
 
 	let mut client = Client::builder(token)
@@ -90,6 +155,9 @@ async fn main() {
 	{
 		let mut data = client.data.write().await;
 		data.insert::<BirthdaysDb>(Arc::new(RwLock::new(HashMap::default())));
+		data.insert::<GuildSettingsDb>(Arc::new(RwLock::new(HashMap::default())));
+		data.insert::<UserSettingsDb>(Arc::new(RwLock::new(HashMap::default())));
+		data.insert::<ConversionReplies>(Arc::new(RwLock::new(RecentReplies::default())));
 		data.insert::<TodayDate>(Arc::new(RwLock::new(Date::<Utc>::from_utc(
 			NaiveDate::from_yo(2021, 1),
 			Utc,
@@ -0,0 +1,384 @@
+use futures::stream::TryStreamExt;
+use mongodb::{
+	bson::{doc, Document},
+	options::ReplaceOptions,
+};
+use serde::{Deserialize, Serialize};
+use serenity::{
+	client::Context,
+	framework::standard::{macros::command, Args, CommandResult},
+	model::{
+		channel::Message,
+		id::{GuildId, UserId},
+	},
+	prelude::{RwLock, TypeMapKey},
+};
+use std::{collections::HashMap, env, sync::Arc};
+
+use crate::conversion::{ConversionOptions, DecimalMark, PreferredSystem, Strictness};
+
+pub struct GuildSettingsDb;
+
+impl TypeMapKey for GuildSettingsDb {
+	type Value = Arc<RwLock<HashMap<u64, GuildSettings>>>;
+}
+
+pub struct UserSettingsDb;
+
+impl TypeMapKey for UserSettingsDb {
+	type Value = Arc<RwLock<HashMap<u64, UserSettings>>>;
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GuildSettings {
+	guild_id: String,
+	#[serde(default)]
+	strictness: Strictness,
+	#[serde(default)]
+	unit_system: PreferredSystem,
+	#[serde(default)]
+	decimal_mark: DecimalMark,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserSettings {
+	user_id: String,
+	// takes precedence over the guild's preference when set
+	#[serde(default)]
+	unit_system: Option<PreferredSystem>,
+}
+
+const UNITS_USAGE: &str = "use `!units prefer <metric|us|imperial|both|default>` or, as a server admin, `!units prefer server <metric|us|imperial|both>`";
+
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+pub async fn strictness(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	let guild_id = match msg.guild_id {
+		Some(guild_id) => guild_id,
+		None => return Ok(()),
+	};
+	if args.is_empty() {
+		let strictness = conversion_options(ctx, Some(guild_id), None)
+			.await
+			.strictness;
+		msg.reply(
+			&ctx.http,
+			format!("Conversion strictness for this server is {}", strictness),
+		)
+		.await?;
+		return Ok(());
+	}
+	match args.rest().trim().parse::<Strictness>() {
+		Ok(strictness) => {
+			update_guild_settings(ctx, guild_id, |settings| settings.strictness = strictness).await?;
+			msg.reply(
+				&ctx.http,
+				format!(
+					"Conversion strictness for this server is now {}",
+					strictness
+				),
+			)
+			.await?;
+		}
+		Err(why) => {
+			msg.reply(
+				&ctx.http,
+				format!("{}, use `!strictness <relaxed|normal|strict>`", why),
+			)
+			.await?;
+		}
+	}
+	Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+pub async fn decimal(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	let guild_id = match msg.guild_id {
+		Some(guild_id) => guild_id,
+		None => return Ok(()),
+	};
+	if args.is_empty() {
+		let decimal_mark = conversion_options(ctx, Some(guild_id), None)
+			.await
+			.decimal_mark;
+		msg.reply(
+			&ctx.http,
+			format!("This server writes decimals with a {}", decimal_mark),
+		)
+		.await?;
+		return Ok(());
+	}
+	match args.rest().trim().parse::<DecimalMark>() {
+		Ok(decimal_mark) => {
+			update_guild_settings(ctx, guild_id, |settings| {
+				settings.decimal_mark = decimal_mark
+			})
+			.await?;
+			msg.reply(
+				&ctx.http,
+				format!("This server now writes decimals with a {}", decimal_mark),
+			)
+			.await?;
+		}
+		Err(why) => {
+			msg.reply(&ctx.http, format!("{}, use `!decimal <point|comma>`", why))
+				.await?;
+		}
+	}
+	Ok(())
+}
+
+#[command]
+#[sub_commands(prefer)]
+pub async fn units(ctx: &Context, msg: &Message) -> CommandResult {
+	let user_system = {
+		let data_read = ctx.data.read().await;
+		let settings_lock = data_read
+			.get::<UserSettingsDb>()
+			.expect("expected a UserSettingsDb")
+			.clone();
+		let settings = settings_lock.read().await;
+		settings
+			.get(&msg.author.id.0)
+			.and_then(|settings| settings.unit_system)
+	};
+	let system = conversion_options(ctx, msg.guild_id, None).await.system;
+	let reply = match (user_system, msg.guild_id) {
+		(Some(user_system), Some(_)) => format!(
+			"You prefer {} units, this server prefers {}",
+			user_system, system
+		),
+		(Some(user_system), None) => format!("You prefer {} units", user_system),
+		(None, Some(_)) => format!("This server prefers {} units, {}", system, UNITS_USAGE),
+		(None, None) => format!("Conversions use {} units, {}", system, UNITS_USAGE),
+	};
+	msg.reply(&ctx.http, reply).await?;
+	Ok(())
+}
+
+#[command]
+#[sub_commands(server)]
+async fn prefer(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	let system = match args.rest().trim() {
+		"default" => None,
+		system => match system.parse::<PreferredSystem>() {
+			Ok(system) => Some(system),
+			Err(why) => {
+				msg.reply(&ctx.http, format!("{}, {}", why, UNITS_USAGE))
+					.await?;
+				return Ok(());
+			}
+		},
+	};
+	update_user_settings(ctx, msg.author.id, |settings| settings.unit_system = system).await?;
+	let reply = match system {
+		Some(system) => format!("Your conversions now use {} units", system),
+		None => String::from("Your conversions now follow the server's preference"),
+	};
+	msg.reply(&ctx.http, reply).await?;
+	Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn server(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	let guild_id = match msg.guild_id {
+		Some(guild_id) => guild_id,
+		None => return Ok(()),
+	};
+	match args.rest().trim().parse::<PreferredSystem>() {
+		Ok(system) => {
+			update_guild_settings(ctx, guild_id, |settings| settings.unit_system = system).await?;
+			msg.reply(
+				&ctx.http,
+				format!("Conversions in this server now use {} units", system),
+			)
+			.await?;
+		}
+		Err(why) => {
+			msg.reply(&ctx.http, format!("{}, {}", why, UNITS_USAGE))
+				.await?;
+		}
+	}
+	Ok(())
+}
+
+// utility function for reading all guild settings into memory
+pub async fn guild_settings_update(ctx: &Context) -> CommandResult {
+	let connection_string = env::var("DB_CONNECTION_STRING").expect("Database connection string not found");
+	let mut settings_dict: HashMap<u64, GuildSettings> = HashMap::new();
+	{
+		let client = mongodb::Client::with_uri_str(connection_string).await?;
+		let db = client.database("discord-bot");
+		let guild_settings = db.collection::<GuildSettings>("guild_settings");
+		let mut cursor = guild_settings.find(doc! {}, None).await?;
+		while let Some(settings) = cursor.try_next().await? {
+			settings_dict.insert(settings.guild_id.parse()?, settings);
+		}
+	}
+	let data_lock = {
+		let data_write = ctx.data.read().await;
+		data_write
+			.get::<GuildSettingsDb>()
+			.expect("Expected a GuildSettingsDb")
+			.clone()
+	};
+	{
+		let mut settings_db = data_lock.write().await;
+		*settings_db = settings_dict;
+	}
+	Ok(())
+}
+
+// changes a guild's settings in memory and stores them in the DB
+async fn update_guild_settings(
+	ctx: &Context,
+	guild_id: GuildId,
+	update: impl FnOnce(&mut GuildSettings) + Send,
+) -> CommandResult {
+	let data_lock = {
+		let data_read = ctx.data.read().await;
+		data_read
+			.get::<GuildSettingsDb>()
+			.expect("Expected a GuildSettingsDb")
+			.clone()
+	};
+	let settings = {
+		let mut settings_db = data_lock.write().await;
+		let settings = settings_db
+			.entry(guild_id.0)
+			.or_insert_with(|| GuildSettings {
+				guild_id: guild_id.0.to_string(),
+				..GuildSettings::default()
+			});
+		update(settings);
+		settings.clone()
+	};
+	store_settings(
+		"guild_settings",
+		doc! {"guild_id": &settings.guild_id},
+		settings,
+	)
+	.await
+}
+
+// utility function for reading all user settings into memory
+pub async fn user_settings_update(ctx: &Context) -> CommandResult {
+	let connection_string = env::var("DB_CONNECTION_STRING").expect("Database connection string not found");
+	let mut settings_dict: HashMap<u64, UserSettings> = HashMap::new();
+	{
+		let client = mongodb::Client::with_uri_str(connection_string).await?;
+		let db = client.database("discord-bot");
+		let user_settings = db.collection::<UserSettings>("user_settings");
+		let mut cursor = user_settings.find(doc! {}, None).await?;
+		while let Some(settings) = cursor.try_next().await? {
+			settings_dict.insert(settings.user_id.parse()?, settings);
+		}
+	}
+	let data_lock = {
+		let data_write = ctx.data.read().await;
+		data_write
+			.get::<UserSettingsDb>()
+			.expect("Expected a UserSettingsDb")
+			.clone()
+	};
+	{
+		let mut settings_db = data_lock.write().await;
+		*settings_db = settings_dict;
+	}
+	Ok(())
+}
+
+// changes a user's settings in memory and stores them in the DB
+async fn update_user_settings(
+	ctx: &Context,
+	user_id: UserId,
+	update: impl FnOnce(&mut UserSettings) + Send,
+) -> CommandResult {
+	let data_lock = {
+		let data_read = ctx.data.read().await;
+		data_read
+			.get::<UserSettingsDb>()
+			.expect("Expected a UserSettingsDb")
+			.clone()
+	};
+	let settings = {
+		let mut settings_db = data_lock.write().await;
+		let settings = settings_db
+			.entry(user_id.0)
+			.or_insert_with(|| UserSettings {
+				user_id: user_id.0.to_string(),
+				..UserSettings::default()
+			});
+		update(settings);
+		settings.clone()
+	};
+	store_settings(
+		"user_settings",
+		doc! {"user_id": &settings.user_id},
+		settings,
+	)
+	.await
+}
+
+// inserts or replaces the settings document matching `filter`
+async fn store_settings<T: Serialize + Send + Sync>(collection: &str, filter: Document, settings: T) -> CommandResult {
+	let connection_string = env::var("DB_CONNECTION_STRING").expect("Database connection string not found");
+	{
+		let client = mongodb::Client::with_uri_str(connection_string).await?;
+		let db = client.database("discord-bot");
+		let collection = db.collection::<T>(collection);
+		collection
+			.replace_one(
+				filter,
+				settings,
+				ReplaceOptions::builder().upsert(true).build(),
+			)
+			.await?;
+	}
+	Ok(())
+}
+
+// how a user's message should be converted, their own unit preference beats the guild's
+// and DMs use the defaults for everything else
+pub async fn conversion_options(
+	ctx: &Context,
+	guild_id: Option<GuildId>,
+	user_id: Option<UserId>,
+) -> ConversionOptions {
+	let settings = match guild_id {
+		Some(guild_id) => {
+			let data_read = ctx.data.read().await;
+			let settings_lock = data_read
+				.get::<GuildSettingsDb>()
+				.expect("expected a GuildSettingsDb")
+				.clone();
+			let settings = settings_lock.read().await;
+			settings.get(&guild_id.0).cloned().unwrap_or_default()
+		}
+		None => GuildSettings::default(),
+	};
+	let user_system = match user_id {
+		Some(user_id) => {
+			let data_read = ctx.data.read().await;
+			let settings_lock = data_read
+				.get::<UserSettingsDb>()
+				.expect("expected a UserSettingsDb")
+				.clone();
+			let settings = settings_lock.read().await;
+			settings
+				.get(&user_id.0)
+				.and_then(|settings| settings.unit_system)
+		}
+		None => None,
+	};
+	ConversionOptions {
+		strictness: settings.strictness,
+		system: user_system.unwrap_or(settings.unit_system),
+		decimal_mark: settings.decimal_mark,
+	}
+}